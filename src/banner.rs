@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use nbt::Value;
+
+use crate::block::{banner_model, init_blocks, Block};
+use crate::dye::{self, DYES};
+use crate::resources::textures::CpuImage;
+use crate::schematic::BlockEntity;
+use crate::{AppLoadState, BlockWorld};
+
+/// Texture with the pole and bar, which the pattern layers get drawn on top of
+const BANNER_BASE: &str = "entity/banner_base.png";
+
+/// Pattern ids used by banners from before 1.20.5, which stored abbreviations
+fn legacy_pattern(code: &str) -> Option<&'static str> {
+    let pattern = match code {
+        "b" => "base",
+        "bl" => "square_bottom_left",
+        "br" => "square_bottom_right",
+        "tl" => "square_top_left",
+        "tr" => "square_top_right",
+        "bs" => "stripe_bottom",
+        "ts" => "stripe_top",
+        "ls" => "stripe_left",
+        "rs" => "stripe_right",
+        "cs" => "stripe_center",
+        "ms" => "stripe_middle",
+        "drs" => "stripe_downright",
+        "dls" => "stripe_downleft",
+        "ss" => "small_stripes",
+        "cr" => "cross",
+        "sc" => "straight_cross",
+        "bt" => "triangle_bottom",
+        "tt" => "triangle_top",
+        "bts" => "triangles_bottom",
+        "tts" => "triangles_top",
+        "ld" => "diagonal_left",
+        "rd" => "diagonal_up_right",
+        "lud" => "diagonal_up_left",
+        "rud" => "diagonal_right",
+        "mc" => "circle",
+        "mr" => "rhombus",
+        "vh" => "half_vertical",
+        "hh" => "half_horizontal",
+        "vhr" => "half_vertical_right",
+        "hhb" => "half_horizontal_bottom",
+        "bo" => "border",
+        "cbo" => "curly_border",
+        "gra" => "gradient",
+        "gru" => "gradient_up",
+        "bri" => "bricks",
+        "glb" => "globe",
+        "cre" => "creeper",
+        "sku" => "skull",
+        "flo" => "flower",
+        "moj" => "mojang",
+        "pig" => "piglin",
+        _ => return None,
+    };
+    Some(pattern)
+}
+
+/// A pattern layer, as the texture location of the pattern and the name of its dye
+type Layer = (String, String);
+
+fn pattern_texture(pattern: &str) -> String {
+    let (namespace, path) = pattern.split_once(':').unwrap_or(("minecraft", pattern));
+    format!("{}:entity/banner/{}.png", namespace, path)
+}
+
+fn read_layer(value: &Value) -> Option<Layer> {
+    let Value::Compound(layer) = value else {
+        return None;
+    };
+    match (layer.get("pattern"), layer.get("color")) {
+        (Some(pattern), Some(Value::String(color))) => {
+            let pattern = match pattern {
+                Value::String(pattern) => pattern.as_str(),
+                // Patterns that aren't registered are stored inline
+                Value::Compound(pattern) => match pattern.get("asset_id") {
+                    Some(Value::String(asset_id)) => asset_id.as_str(),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            Some((pattern_texture(pattern), color.clone()))
+        }
+        _ => {
+            // Before 1.20.5
+            let Some(Value::String(code)) = layer.get("Pattern") else {
+                return None;
+            };
+            let Some(Value::Int(color)) = layer.get("Color") else {
+                return None;
+            };
+            let color = DYES.get(usize::try_from(*color).ok()?)?;
+            Some((pattern_texture(legacy_pattern(code)?), color.to_string()))
+        }
+    }
+}
+
+/// The layers of a banner from the bottom up, starting with its base color
+fn banner_layers(block: &str, block_entity: Option<&BlockEntity>) -> Vec<Layer> {
+    let name = block.split('[').next().unwrap_or(block);
+    let name = name.trim_start_matches("minecraft:");
+    let base_color = name
+        .strip_suffix("_wall_banner")
+        .or_else(|| name.strip_suffix("_banner"))
+        .unwrap_or("white");
+    let mut layers = vec![(pattern_texture("base"), base_color.to_string())];
+
+    let patterns = block_entity.and_then(|block_entity| {
+        block_entity
+            .get("patterns")
+            .or_else(|| block_entity.get("Patterns"))
+    });
+    if let Some(Value::List(patterns)) = patterns {
+        layers.extend(patterns.iter().filter_map(read_layer));
+    }
+    layers
+}
+
+/// Pattern textures and the materials composited from them, shared by banners with the same
+/// pattern stack
+#[derive(Default)]
+struct BannerTextures {
+    textures: HashMap<String, Option<CpuImage>>,
+    materials: HashMap<Vec<Layer>, Handle<StandardMaterial>>,
+}
+
+impl BannerTextures {
+    fn texture(&mut self, location: &str) -> Option<&CpuImage> {
+        self.textures
+            .entry(location.to_string())
+            .or_insert_with(|| {
+                CpuImage::load(location)
+                    .inspect_err(|err| warn!("Could not load banner texture {}: {}", location, err))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Draws the layers on top of the banner base, tinting each with its dye color
+    fn composite(&mut self, layers: &[Layer]) -> Option<CpuImage> {
+        let base = self.texture(BANNER_BASE)?;
+        let mut composite = CpuImage {
+            width: base.width,
+            height: base.height,
+            rgba: base.rgba.clone(),
+        };
+
+        for (pattern, color) in layers {
+            let Some(tint) = dye::diffuse_color(color) else {
+                warn!("Unknown banner color {}", color);
+                continue;
+            };
+            let tint = tint.to_srgba();
+            let Some(texture) = self.texture(pattern) else {
+                continue;
+            };
+            let width = texture.width.min(composite.width);
+            let height = texture.height.min(composite.height);
+            for y in 0..height {
+                for x in 0..width {
+                    let [r, g, b, a] = texture.pixel(x, y);
+                    if a == 0 {
+                        continue;
+                    }
+                    let alpha = a as f32 / 255.0;
+                    let idx = ((y * composite.width + x) * 4) as usize;
+                    let dst = &mut composite.rgba[idx..idx + 4];
+                    for (channel, (src, tint)) in [(r, tint.red), (g, tint.green), (b, tint.blue)]
+                        .into_iter()
+                        .enumerate()
+                    {
+                        let src = src as f32 * tint;
+                        dst[channel] = (dst[channel] as f32).lerp(src, alpha).round() as u8;
+                    }
+                    dst[3] = dst[3].max(a);
+                }
+            }
+        }
+        Some(composite)
+    }
+
+    fn material(
+        &mut self,
+        layers: Vec<Layer>,
+        images: &mut Assets<Image>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Option<(Handle<StandardMaterial>, Vec2)> {
+        let base = self.texture(BANNER_BASE)?;
+        let size = Vec2::new(base.width as f32, base.height as f32);
+        if let Some(material) = self.materials.get(&layers) {
+            return Some((material.clone(), size));
+        }
+
+        let composite = self.composite(&layers)?;
+        let material = materials.add(StandardMaterial {
+            base_color_texture: Some(images.add(composite.into_image())),
+            perceptual_roughness: 1.0,
+            reflectance: 0.0,
+            alpha_mode: AlphaMode::Mask(0.5),
+            ..default()
+        });
+        self.materials.insert(layers, material.clone());
+        Some((material, size))
+    }
+}
+
+/// Adds the flags of banners as they're spawned, both when the schematic is loaded and when
+/// they're placed or replaced while editing
+fn spawn_banners(
+    mut commands: Commands,
+    mut textures: Local<BannerTextures>,
+    block_world: Res<BlockWorld>,
+    blocks: Query<(Entity, &Block), Changed<Block>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (entity, block) in blocks.iter() {
+        if !block.is_world_block(entity, &block_world) {
+            continue;
+        }
+        let pos = block.pos();
+        let block = block_world
+            .blocks
+            .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+        let Some(model) = banner_model(block) else {
+            continue;
+        };
+
+        let layers = banner_layers(block, block_world.block_entities.get(&pos));
+        let Some((material, size)) = textures.material(layers, &mut images, &mut materials) else {
+            continue;
+        };
+        let banner = commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(model.mesh(size)),
+                material,
+                ..default()
+            })
+            .id();
+        commands.entity(entity).add_child(banner);
+    }
+}
+
+pub struct BannerPlugin;
+
+impl Plugin for BannerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            spawn_banners
+                .after(init_blocks)
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
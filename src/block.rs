@@ -1,3 +1,12 @@
+mod clip;
+mod effect;
+mod entity;
+mod look;
+
+pub use clip::{BlockMaterial, SectionClip};
+pub use entity::{banner_model, sign_text_layout, SignTextLayout};
+pub use look::BlockLook;
+
 use std::collections::HashMap;
 
 use bevy::prelude::*;
@@ -11,9 +20,17 @@ use minecraft_assets::schemas::blockstates::ModelProperties;
 use minecraft_assets::schemas::models::{Axis, BlockFace, Element, ElementFace, Textures};
 
 use crate::resources::textures::{resolve_textures_completely, TextureAtlas};
-use crate::AppLoadState;
+use crate::{AppLoadState, BlockWorld};
 
-fn decode_props(props: &str) -> HashMap<&str, StateValue> {
+/// Splits a block state like `minecraft:chest[facing=north]` into its name and properties
+pub fn split_block_state(block: &str) -> (&str, &str) {
+    match block.split_once('[') {
+        Some((name, props)) => (name, props.trim_end_matches(']')),
+        None => (block, ""),
+    }
+}
+
+pub fn parse_props(props: &str) -> HashMap<&str, &str> {
     let mut res = HashMap::new();
     if props.is_empty() {
         return res;
@@ -21,20 +38,23 @@ fn decode_props(props: &str) -> HashMap<&str, StateValue> {
 
     for prop in props.split(',') {
         let (k, v) = prop.split_once('=').unwrap();
-        let v = StateValue::String(v.to_string());
         res.insert(k, v);
     }
     res
 }
 
+fn decode_props(props: &str) -> HashMap<&str, StateValue> {
+    parse_props(props)
+        .into_iter()
+        .map(|(k, v)| (k, StateValue::String(v.to_string())))
+        .collect()
+}
+
 fn get_block_model(
     asset_pack: &AssetPack,
     block: &str,
 ) -> Result<(Vec<ModelProperties>, Option<Color>)> {
-    let (name, props) = match block.split_once('[') {
-        Some((name, props)) => (name, props.trim_end_matches(']')),
-        None => (block, ""),
-    };
+    let (name, props) = split_block_state(block);
     let blockstates = asset_pack.load_blockstates(name)?;
     let props = decode_props(props);
 
@@ -64,18 +84,14 @@ fn get_tint_for_block(
     tint_idx: usize,
 ) -> Color {
     if block_name == "minecraft:redstone_wire" && tint_idx == 0 {
-        let power: f32 = block_props
+        let power = block_props
             .get("power")
             .map(|prop| match prop {
                 StateValue::String(str) => str.parse().unwrap_or_default(),
-                _ => 0.0,
+                _ => 0,
             })
             .unwrap_or_default();
-        let f = power / 15.0;
-        let r = f * 0.6 + if f > 0.0 { 0.4 } else { 0.3 };
-        let g = (f * f * 0.7 - 0.5).clamp(0.0, 1.0);
-        let b = (f * f * 0.6 - 0.7).clamp(0.0, 1.0);
-        Color::srgb(r, g, b)
+        redstone_power_color(power)
     } else {
         warn!(
             "Unknown tint with block {} and idx {}",
@@ -85,6 +101,15 @@ fn get_tint_for_block(
     }
 }
 
+/// Color of redstone dust carrying a signal strength from 0 to 15
+pub fn redstone_power_color(power: u8) -> Color {
+    let f = power as f32 / 15.0;
+    let r = f * 0.6 + if f > 0.0 { 0.4 } else { 0.3 };
+    let g = (f * f * 0.7 - 0.5).clamp(0.0, 1.0);
+    let b = (f * f * 0.6 - 0.7).clamp(0.0, 1.0);
+    Color::srgb(r, g, b)
+}
+
 fn element_mesh(
     element: &Element,
     atlas: &TextureAtlas,
@@ -234,6 +259,21 @@ fn element_mesh(
     )
 }
 
+fn model_rotation(model: &ProcessedModel) -> Quat {
+    let (x, y) = model.model_rot;
+    Quat::from_rotation_y((-y as f32).to_radians())
+        * Quat::from_rotation_x((-x as f32).to_radians())
+}
+
+fn element_rotation(element: &Element) -> Quat {
+    let rot_angle = element.rotation.angle.to_radians();
+    match element.rotation.axis {
+        Axis::X => Quat::from_rotation_x(rot_angle),
+        Axis::Y => Quat::from_rotation_y(rot_angle),
+        Axis::Z => Quat::from_rotation_z(rot_angle),
+    }
+}
+
 fn rot_vert_with_orig(rot: Quat, orig: [f32; 3], vert: [f32; 3]) -> [f32; 3] {
     let orig = Vec3::from_array(orig);
     let v = Vec3::from_array(vert) - orig;
@@ -255,12 +295,22 @@ pub struct ElementMesh {
     pub has_transparency: bool,
 }
 
-pub fn create_mesh_for_block(
+/// Triangles of a block's models in pixels, with their texture coordinates in the atlas
+pub struct BlockGeometry {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+    pub has_transparency: bool,
+}
+
+/// Geometry of a block's models along with its tint, shared by the meshes in the world and the
+/// icons of the interface
+pub fn block_geometry(
     block: &str,
     atlas: &TextureAtlas,
     block_models: &BlockModels,
-    mesh_assets: &mut Assets<Mesh>,
-) -> (ElementMesh, Option<Color>) {
+) -> (BlockGeometry, Option<Color>) {
     let models = &block_models.0[block];
 
     let mut positions: Vec<[f32; 3]> = Vec::new();
@@ -287,13 +337,8 @@ pub fn create_mesh_for_block(
                 has_transparency = true;
             }
 
-            let model_rot = Quat::from_rotation_y(model_rot.y) * Quat::from_rotation_x(model_rot.x);
-            let rot_angle = element.rotation.angle.to_radians();
-            let elem_rot = match element.rotation.axis {
-                Axis::X => Quat::from_rotation_x(rot_angle),
-                Axis::Y => Quat::from_rotation_y(rot_angle),
-                Axis::Z => Quat::from_rotation_z(rot_angle),
-            };
+            let model_rot = model_rotation(model);
+            let elem_rot = element_rotation(element);
             let mat = Transform::from_rotation(model_rot * elem_rot).compute_matrix();
 
             let indices_offset = positions.len() as u32;
@@ -357,40 +402,149 @@ pub fn create_mesh_for_block(
         }
     }
 
+    if let Some(model) = entity::block_entity_model(block) {
+        let model_has_transparency =
+            model.append_geometry(atlas, &mut positions, &mut normals, &mut uvs, &mut indices);
+        if model_has_transparency {
+            has_transparency = true;
+        }
+    }
+
+    let geometry = BlockGeometry {
+        positions,
+        normals,
+        uvs,
+        indices,
+        has_transparency,
+    };
+    (geometry, models.1)
+}
+
+pub fn create_mesh_for_block(
+    block: &str,
+    atlas: &TextureAtlas,
+    block_models: &BlockModels,
+    mesh_assets: &mut Assets<Mesh>,
+) -> (ElementMesh, Option<Color>) {
+    let (geometry, tint) = block_geometry(block, atlas, block_models);
     let mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::default(),
     )
-    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
-    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
-    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
-    .with_inserted_indices(Indices::U32(indices));
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, geometry.positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, geometry.normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, geometry.uvs)
+    .with_inserted_indices(Indices::U32(geometry.indices));
     let meshes = ElementMesh {
         mesh: mesh_assets.add(mesh),
-        has_transparency,
+        has_transparency: geometry.has_transparency,
     };
-    (meshes, models.1)
+    (meshes, tint)
 }
 
 #[derive(Resource)]
 pub struct BlockModels(HashMap<String, (Vec<ProcessedModel>, Option<Color>)>);
 
 pub fn get_block_models_for(asset_pack: &AssetPack, schem: &Schematic) -> Result<BlockModels> {
-    let mut models = HashMap::new();
+    let mut models = BlockModels(HashMap::new());
     let (sx, sy, sz) = schem.blocks.size();
     for x in 0..sx {
         for y in 0..sy {
             for z in 0..sz {
-                let block = schem.blocks.get_block_at(x, y, z);
-                if !models.contains_key(block) {
-                    let (model_properties, tint) = get_block_model(&asset_pack, block)?;
-                    let model = process_model(&asset_pack, model_properties)?;
-                    models.insert(block.to_string(), (model, tint));
-                }
+                models.insert(asset_pack, schem.blocks.get_block_at(x, y, z))?;
             }
         }
     }
-    Ok(BlockModels(models))
+    Ok(models)
+}
+
+impl BlockModels {
+    /// Loads the models of a block state, or of a block model like `minecraft:block/item_frame`
+    /// which gets rendered on its own
+    pub fn insert(&mut self, asset_pack: &AssetPack, block: &str) -> Result<()> {
+        if self.0.contains_key(block) {
+            return Ok(());
+        }
+        let (model_properties, tint) = if block.contains('/') {
+            let model = serde_json::from_value(serde_json::json!({ "model": block }))?;
+            (vec![model], None)
+        } else {
+            get_block_model(asset_pack, block)?
+        };
+        let model = process_model(asset_pack, model_properties)?;
+        self.0.insert(block.to_string(), (model, tint));
+        Ok(())
+    }
+
+    /// The boxes of the elements of a block's models. Blocks drawn entirely by a block entity
+    /// renderer have no elements.
+    pub fn element_boxes(&self, block: &str) -> Vec<ElementBox> {
+        let Some((models, _)) = self.0.get(block) else {
+            return Vec::new();
+        };
+        let mut boxes = Vec::new();
+        for model in models {
+            let model_rot = model_rotation(model);
+            for element in &model.elements {
+                let origin = Vec3::from_array(element.rotation.origin);
+                let center = Vec3::splat(8.0);
+                boxes.push(ElementBox {
+                    from: Vec3::from_array(element.from),
+                    to: Vec3::from_array(element.to),
+                    transform: Mat4::from_rotation_translation(model_rot, center)
+                        * Mat4::from_translation(origin - center)
+                        * Mat4::from_quat(element_rotation(element))
+                        * Mat4::from_translation(-origin),
+                    axis_aligned: element.rotation.angle == 0.0,
+                });
+            }
+        }
+        boxes
+    }
+
+    /// Adds a model whose elements were resolved elsewhere, like the models of items
+    pub fn insert_elements(&mut self, name: &str, textures: Textures, elements: Vec<Element>) {
+        let model = ProcessedModel {
+            model_rot: (0, 0),
+            uv_lock: false,
+            textures,
+            elements,
+        };
+        self.0.insert(name.to_string(), (vec![model], None));
+    }
+}
+
+/// A model element as a box in pixels
+pub struct ElementBox {
+    pub from: Vec3,
+    pub to: Vec3,
+    /// The element rotation followed by the model rotation
+    pub transform: Mat4,
+    /// Whether the box stays axis aligned, which is the case unless the element is rotated
+    pub axis_aligned: bool,
+}
+
+impl ElementBox {
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|corner| {
+            let p = Vec3::select(
+                BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                self.to,
+                self.from,
+            );
+            self.transform.transform_point3(p)
+        })
+    }
+
+    /// Axis aligned bounds of the rotated box
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let corners = self.corners();
+        let min = corners.iter().fold(Vec3::INFINITY, |min, &p| min.min(p));
+        let max = corners
+            .iter()
+            .fold(Vec3::NEG_INFINITY, |max, &p| max.max(p));
+        (min, max)
+    }
 }
 
 #[derive(Debug)]
@@ -433,29 +587,36 @@ fn process_model(
 #[derive(Default)]
 struct BlockMaterials {
     base: StandardMaterial,
-    opaque: Handle<StandardMaterial>,
-    transparent: Handle<StandardMaterial>,
+    opaque: Handle<BlockMaterial>,
+    transparent: Handle<BlockMaterial>,
     /// mapping from tint color to material
-    tints: HashMap<u32, Handle<StandardMaterial>>,
+    tints: HashMap<u32, Handle<BlockMaterial>>,
+}
+
+/// A block material that isn't clipped yet, the section box is applied once it's added
+fn block_material(base: StandardMaterial) -> BlockMaterial {
+    BlockMaterial {
+        base,
+        extension: SectionClip::default(),
+    }
 }
 
 impl BlockMaterials {
-    fn new(materials: &mut Assets<StandardMaterial>, atlas: &TextureAtlas) -> Self {
+    fn new(materials: &mut Assets<BlockMaterial>, atlas: &TextureAtlas) -> Self {
         let base = StandardMaterial {
             base_color_texture: Some(atlas.image.clone()),
             perceptual_roughness: 1.0,
             reflectance: 0.0,
-            fog_enabled: false,
             alpha_mode: AlphaMode::Blend,
             ..default()
         };
 
         Self {
-            opaque: materials.add(StandardMaterial {
+            opaque: materials.add(block_material(StandardMaterial {
                 alpha_mode: AlphaMode::Mask(0.5),
                 ..base.clone()
-            }),
-            transparent: materials.add(base.clone()),
+            })),
+            transparent: materials.add(block_material(base.clone())),
             tints: Default::default(),
             base,
         }
@@ -464,16 +625,16 @@ impl BlockMaterials {
     fn get_or_add_tint(
         &mut self,
         tint: Color,
-        materials: &mut Assets<StandardMaterial>,
-    ) -> Handle<StandardMaterial> {
+        materials: &mut Assets<BlockMaterial>,
+    ) -> Handle<BlockMaterial> {
         let linear_rgba: LinearRgba = tint.into();
         self.tints
             .entry(linear_rgba.as_u32())
             .or_insert_with(|| {
-                materials.add(StandardMaterial {
+                materials.add(block_material(StandardMaterial {
                     base_color: tint,
                     ..self.base.clone()
-                })
+                }))
             })
             .clone()
     }
@@ -499,6 +660,15 @@ impl Default for BlockPalette {
 }
 
 impl BlockPalette {
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.map.get(name).copied()
+    }
+
+    /// Block states by their palette index
+    pub fn blocks(&self) -> &[String] {
+        &self.blocks
+    }
+
     pub fn get_or_add(&mut self, name: &str) -> usize {
         match self.map.get(name) {
             Some(idx) => *idx,
@@ -522,14 +692,16 @@ struct BlockResources {
 #[derive(Bundle)]
 pub struct BlockBundle {
     pub block: Block,
-    pub pbr: PbrBundle,
+    pub look: BlockLook,
+    pub pbr: MaterialMeshBundle<BlockMaterial>,
 }
 
 impl BlockBundle {
     pub fn new(idx: usize, pos: IVec3) -> Self {
         Self {
-            block: Block { block: idx },
-            pbr: PbrBundle {
+            block: Block { block: idx, pos },
+            look: default(),
+            pbr: MaterialMeshBundle {
                 transform: Transform {
                     translation: pos.as_vec3(),
                     rotation: Quat::IDENTITY,
@@ -544,18 +716,59 @@ impl BlockBundle {
 #[derive(Component)]
 pub struct Block {
     block: usize,
+    pos: IVec3,
 }
 
-fn init_new_blocks(
-    mut blocks: Query<(&Block, &mut Handle<Mesh>, &mut Handle<StandardMaterial>), Added<Block>>,
+impl Block {
+    /// Index of the block state in the `BlockPalette`
+    pub fn index(&self) -> usize {
+        self.block
+    }
+
+    /// Position of the block in the schematic
+    pub fn pos(&self) -> IVec3 {
+        self.pos
+    }
+
+    /// Whether the block is part of the world, rather than shown by an entity like an item
+    /// frame
+    pub fn is_world_block(&self, entity: Entity, block_world: &BlockWorld) -> bool {
+        block_world.entities.get(&self.pos.as_uvec3()) == Some(&entity)
+    }
+
+    /// Changes the block state in place, its mesh and material are set up again after
+    pub fn set_index(&mut self, idx: usize) {
+        self.block = idx;
+    }
+}
+
+/// Sets the mesh and material of blocks as they're spawned or change their state
+pub fn init_blocks(
+    mut commands: Commands,
+    mut blocks: Query<
+        (
+            Entity,
+            Ref<Block>,
+            &mut Handle<Mesh>,
+            &mut Handle<BlockMaterial>,
+        ),
+        Changed<Block>,
+    >,
     mut res: ResMut<BlockResources>,
     atlas: Res<TextureAtlas>,
     block_models: Res<BlockModels>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
     palette: Res<BlockPalette>,
 ) {
-    for (block, mut mesh_handle, mut mat_handle) in blocks.iter_mut() {
+    for (entity, block, mut mesh_handle, mut mat_handle) in blocks.iter_mut() {
+        // What was drawn for the old state, like sign text, is added again for the new one
+        if !block.is_added() {
+            commands
+                .entity(entity)
+                .despawn_descendants()
+                .remove::<look::OwnMaterial>();
+        }
         let res = &mut *res;
         let (mesh, tint) = match res.meshes.get(&block.block) {
             Some(mesh) => mesh,
@@ -587,7 +800,7 @@ fn init_new_blocks(
 
 fn init_block_resources(
     mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
     atlas: Res<TextureAtlas>,
 ) {
     commands.insert_resource(BlockResources {
@@ -600,12 +813,14 @@ pub struct BlockPlugin;
 
 impl Plugin for BlockPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            init_new_blocks.run_if(in_state(AppLoadState::Finished)),
-        )
-        .add_systems(OnEnter(AppLoadState::Finished), init_block_resources)
-        .init_resource::<BlockPalette>()
-        .init_resource::<BlockResources>();
+        app.add_systems(Update, init_blocks.run_if(in_state(AppLoadState::Finished)))
+            .add_systems(OnEnter(AppLoadState::Finished), init_block_resources)
+            .add_plugins((
+                clip::BlockClipPlugin,
+                effect::BlockEffectPlugin,
+                look::BlockLookPlugin,
+            ))
+            .init_resource::<BlockPalette>()
+            .init_resource::<BlockResources>();
     }
 }
@@ -0,0 +1,90 @@
+//! Material extension that cuts blocks away outside of the section box and draws ghosted
+//! blocks thinned out. Blocks are tested by the origin of their mesh, so a whole block is
+//! either kept or cut, and it all happens in the shader without touching the meshes.
+
+use bevy::asset::load_internal_asset;
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+const SECTION_CLIP_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6d63_7265_6e64_6572_0000_0000_0000_0033);
+
+/// Bound used for sides of the box that don't cut anything
+const UNBOUNDED: f32 = 1.0e9;
+
+/// Material used for blocks and the entities drawn with the block atlas
+pub type BlockMaterial = ExtendedMaterial<StandardMaterial, SectionClip>;
+
+/// Box of blocks that are drawn, and the part of it that's ghosted, in schematic blocks with
+/// the maximum excluded
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, PartialEq)]
+pub struct SectionClip {
+    #[uniform(100)]
+    pub min: Vec3,
+    #[uniform(100)]
+    pub max: Vec3,
+    #[uniform(100)]
+    pub ghost_min: Vec3,
+    #[uniform(100)]
+    pub ghost_max: Vec3,
+    /// Ghosts every block drawn with the material, regardless of the ghost box
+    #[uniform(100)]
+    pub ghost_all: u32,
+}
+
+impl Default for SectionClip {
+    fn default() -> Self {
+        Self {
+            min: Vec3::splat(-UNBOUNDED),
+            max: Vec3::splat(UNBOUNDED),
+            ghost_min: Vec3::ZERO,
+            ghost_max: Vec3::ZERO,
+            ghost_all: 0,
+        }
+    }
+}
+
+impl SectionClip {
+    /// Narrows the box down to the blocks from `min` up to but excluding `max`
+    pub fn cut(&mut self, min: Vec3, max: Vec3) {
+        self.min = self.min.max(min);
+        self.max = self.max.min(max);
+    }
+
+    /// Ghosts a whole layer of the schematic
+    pub fn ghost_layer(&mut self, y: i32) {
+        self.ghost_min = Vec3::new(-UNBOUNDED, y as f32, -UNBOUNDED);
+        self.ghost_max = Vec3::new(UNBOUNDED, y as f32 + 1.0, UNBOUNDED);
+    }
+
+    /// Whether a block is drawn, matching the test in the shader
+    pub fn contains(&self, pos: IVec3) -> bool {
+        let pos = pos.as_vec3();
+        pos.cmpge(self.min).all() && pos.cmplt(self.max).all()
+    }
+}
+
+impl MaterialExtension for SectionClip {
+    fn fragment_shader() -> ShaderRef {
+        SECTION_CLIP_SHADER_HANDLE.into()
+    }
+
+    fn prepass_fragment_shader() -> ShaderRef {
+        SECTION_CLIP_SHADER_HANDLE.into()
+    }
+}
+
+pub struct BlockClipPlugin;
+
+impl Plugin for BlockClipPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            SECTION_CLIP_SHADER_HANDLE,
+            "section_clip.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<BlockMaterial>::default());
+    }
+}
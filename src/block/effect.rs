@@ -0,0 +1,393 @@
+//! Blocks with effects that can't be expressed as block models: beacon beams and the starfield
+//! surfaces of end portals and end gateways. They're spawned as extra meshes below the block
+//! entity, in the same pixel units as block models.
+
+use bevy::asset::load_internal_asset;
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use bevy::render::texture::{
+    ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+use bevy::utils::{HashMap, HashSet};
+use nbt::Value;
+
+use super::{init_blocks, split_block_state, Block, BlockModels};
+use crate::dye;
+use crate::editor::BlocksChanged;
+use crate::light::blocks_light;
+use crate::model::QuadMesh;
+use crate::{AppLoadState, BlockWorld};
+
+const END_PORTAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6d63_7265_6e64_6572_0000_0000_0000_0032);
+
+/// Radius of the inner beacon beam and of the translucent glow around it, in blocks
+const BEAM_RADIUS: f32 = 0.2;
+const BEAM_GLOW_RADIUS: f32 = 0.25;
+const BEAM_GLOW_ALPHA: f32 = 0.125;
+/// How far beams continue above the top of the schematic, in blocks
+const BEAM_EXTRA_HEIGHT: i32 = 256;
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct EndPortalMaterial {
+    /// Number of star layers, vanilla draws 15 for portals and 16 for gateways
+    #[uniform(0)]
+    layers: u32,
+    #[texture(1)]
+    #[sampler(2)]
+    sky: Handle<Image>,
+    #[texture(3)]
+    #[sampler(4)]
+    portal: Handle<Image>,
+}
+
+impl Material for EndPortalMaterial {
+    fn fragment_shader() -> ShaderRef {
+        END_PORTAL_SHADER_HANDLE.into()
+    }
+}
+
+/// Loads a texture that gets tiled across a surface
+fn load_repeating(asset_server: &AssetServer, path: &'static str) -> Handle<Image> {
+    asset_server.load_with_settings(path, |settings: &mut ImageLoaderSettings| {
+        settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            ..ImageSamplerDescriptor::nearest()
+        });
+    })
+}
+
+fn block_name(block: &str) -> &str {
+    split_block_state(block).0.trim_start_matches("minecraft:")
+}
+
+fn block_at(block_world: &BlockWorld, pos: IVec3) -> Option<&str> {
+    let (sx, sy, sz) = block_world.blocks.size();
+    let in_bounds = pos.x >= 0
+        && pos.y >= 0
+        && pos.z >= 0
+        && (pos.x as usize) < sx
+        && (pos.y as usize) < sy
+        && (pos.z as usize) < sz;
+    in_bounds.then(|| {
+        block_world
+            .blocks
+            .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize)
+    })
+}
+
+/// A part of a beacon beam with a single color, in blocks above the beacon
+struct BeamSection {
+    color: Color,
+    start: i32,
+    height: i32,
+}
+
+/// Follows the beam up from a beacon, coloring it with the stained glass it passes through.
+/// Like in vanilla the beam is only stopped by blocks that stop light, apart from bedrock.
+fn beam_sections(
+    block_world: &BlockWorld,
+    block_models: &BlockModels,
+    pos: IVec3,
+) -> Vec<BeamSection> {
+    let white = dye::diffuse_color("white").unwrap();
+    let mut sections = vec![BeamSection {
+        color: white,
+        start: 0,
+        height: 1,
+    }];
+
+    let (_, sy, _) = block_world.blocks.size();
+    for y in pos.y + 1..sy as i32 {
+        let block = block_world
+            .blocks
+            .get_block_at(pos.x as usize, y as usize, pos.z as usize);
+        let name = block_name(block);
+        let glass_color = name
+            .strip_suffix("_stained_glass")
+            .or_else(|| name.strip_suffix("_stained_glass_pane"))
+            .and_then(dye::diffuse_color);
+
+        let first = sections.len() == 1;
+        let section = sections.last_mut().unwrap();
+        if let Some(color) = glass_color {
+            // The first glass replaces the white beam, later ones mix with the color below
+            let color = if first {
+                color
+            } else if section.color == color {
+                section.height += 1;
+                continue;
+            } else {
+                let below = section.color.to_srgba();
+                let glass = color.to_srgba();
+                Color::srgb(
+                    (below.red + glass.red) / 2.0,
+                    (below.green + glass.green) / 2.0,
+                    (below.blue + glass.blue) / 2.0,
+                )
+            };
+            sections.push(BeamSection {
+                color,
+                start: y - pos.y,
+                height: 1,
+            });
+        } else if name == "bedrock" || !blocks_light(block_models, block) {
+            section.height += 1;
+        } else {
+            return Vec::new();
+        }
+    }
+
+    sections.last_mut().unwrap().height += BEAM_EXTRA_HEIGHT;
+    sections
+}
+
+fn beam_mesh(sections: &[BeamSection], radius: f32, alpha: f32) -> Mesh {
+    let mut mesh = QuadMesh::default();
+    let r = radius * 16.0;
+    // Vanilla stretches the texture so it tiles faster on thinner beams
+    let v_scale = 0.5 / radius;
+    for section in sections {
+        let bottom = section.start as f32 * 16.0;
+        let height = section.height as f32 * 16.0;
+        let color = section.color.with_alpha(alpha);
+        let uv = [
+            0.0,
+            -(section.start + section.height) as f32 * v_scale,
+            1.0,
+            -section.start as f32 * v_scale,
+        ];
+        for normal in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z] {
+            let center = Vec3::new(8.0, bottom + height / 2.0, 8.0) + normal * r;
+            mesh.quad(center, normal, Vec2::new(r, height / 2.0), uv, color);
+        }
+    }
+    mesh.into_mesh()
+}
+
+/// The starfield surface of an end portal or gateway, leaving out faces between neighbouring
+/// blocks of the same kind
+fn portal_mesh(block_world: &BlockWorld, pos: IVec3, name: &str) -> Mesh {
+    let mut mesh = QuadMesh::default();
+    for normal in [
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ] {
+        let neighbour = block_at(block_world, pos + normal.as_ivec3()).map(block_name);
+        if neighbour == Some(name) {
+            continue;
+        }
+        let center = if name == "end_portal" {
+            // Portals are a thin slab which only shows its top and bottom
+            if normal == Vec3::Y {
+                Vec3::new(8.0, 12.0, 8.0)
+            } else if normal == Vec3::NEG_Y {
+                Vec3::new(8.0, 6.0, 8.0)
+            } else {
+                continue;
+            }
+        } else {
+            Vec3::splat(8.0) + normal * 8.0
+        };
+        mesh.quad(
+            center,
+            normal,
+            Vec2::splat(8.0),
+            [0.0, 0.0, 1.0, 1.0],
+            Color::WHITE,
+        );
+    }
+    mesh.into_mesh()
+}
+
+/// Whether a beacon has a pyramid below it. Schematics without the level stored get the
+/// benefit of the doubt.
+fn beacon_active(block_world: &BlockWorld, pos: IVec3) -> bool {
+    let levels = block_world
+        .block_entities
+        .get(&pos)
+        .and_then(|block_entity| block_entity.get("Levels"));
+    !matches!(levels, Some(Value::Int(..=0)))
+}
+
+struct EffectMaterials {
+    beam: Handle<StandardMaterial>,
+    beam_glow: Handle<StandardMaterial>,
+    end_portal: Handle<EndPortalMaterial>,
+    end_gateway: Handle<EndPortalMaterial>,
+}
+
+impl EffectMaterials {
+    fn new(
+        asset_server: &AssetServer,
+        materials: &mut Assets<StandardMaterial>,
+        portal_materials: &mut Assets<EndPortalMaterial>,
+    ) -> Self {
+        let beam_texture =
+            load_repeating(asset_server, "minecraft/textures/entity/beacon_beam.png");
+        let beam = StandardMaterial {
+            base_color_texture: Some(beam_texture),
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        };
+        let sky = load_repeating(asset_server, "minecraft/textures/environment/end_sky.png");
+        let portal = load_repeating(asset_server, "minecraft/textures/entity/end_portal.png");
+
+        Self {
+            beam_glow: materials.add(StandardMaterial {
+                alpha_mode: AlphaMode::Blend,
+                ..beam.clone()
+            }),
+            beam: materials.add(beam),
+            end_portal: portal_materials.add(EndPortalMaterial {
+                layers: 15,
+                sky: sky.clone(),
+                portal: portal.clone(),
+            }),
+            end_gateway: portal_materials.add(EndPortalMaterial {
+                layers: 16,
+                sky,
+                portal,
+            }),
+        }
+    }
+}
+
+/// Marks the meshes of beacon beams, which are spawned again when their column is edited
+#[derive(Component)]
+struct BeaconBeam;
+
+/// Adds the effects of blocks as they're spawned, both when the schematic is loaded and when
+/// they're placed or replaced while editing. Beacons below edited blocks get their beam worked
+/// out again.
+#[allow(clippy::too_many_arguments)]
+fn spawn_block_effects(
+    mut commands: Commands,
+    mut effect_materials: Local<Option<EffectMaterials>>,
+    mut edits: EventReader<BlocksChanged>,
+    blocks: Query<(Entity, &Block), Changed<Block>>,
+    beams: Query<(Entity, &Parent), With<BeaconBeam>>,
+    block_world: Res<BlockWorld>,
+    block_models: Res<BlockModels>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut portal_materials: ResMut<Assets<EndPortalMaterial>>,
+) {
+    let mut spawned: Vec<_> = blocks
+        .iter()
+        .filter(|(entity, block)| block.is_world_block(*entity, &block_world))
+        .map(|(entity, block)| (entity, block.pos()))
+        .collect();
+    // Each column is only looked at once, below its highest edit, since large edits change
+    // many blocks above each other
+    let mut columns: HashMap<IVec2, i32> = HashMap::new();
+    for pos in edits.read().flat_map(|BlocksChanged(positions)| positions) {
+        let top = columns.entry(pos.xz()).or_insert(pos.y);
+        *top = (*top).max(pos.y);
+    }
+    let mut beacons = HashSet::new();
+    for (column, top) in columns {
+        for y in 0..top {
+            let below = IVec3::new(column.x, y, column.y);
+            if block_at(&block_world, below).map(block_name) != Some("beacon") {
+                continue;
+            }
+            let Some(&entity) = block_world.entities.get(&below.as_uvec3()) else {
+                continue;
+            };
+            if !blocks.contains(entity) && beacons.insert(entity) {
+                spawned.push((entity, below));
+            }
+        }
+    }
+    for (beam, parent) in beams.iter() {
+        if beacons.contains(&parent.get()) {
+            commands.entity(beam).despawn_recursive();
+        }
+    }
+    if spawned.is_empty() {
+        return;
+    }
+
+    let effect_materials = effect_materials.get_or_insert_with(|| {
+        EffectMaterials::new(&asset_server, &mut materials, &mut portal_materials)
+    });
+    for (entity, pos) in spawned {
+        let block = block_world
+            .blocks
+            .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+        let name = block_name(block);
+        match name {
+            "beacon" if beacon_active(&block_world, pos) => {
+                let sections = beam_sections(&block_world, &block_models, pos);
+                if sections.is_empty() {
+                    continue;
+                }
+                commands.entity(entity).with_children(|parent| {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(beam_mesh(&sections, BEAM_RADIUS, 1.0)),
+                            material: effect_materials.beam.clone(),
+                            ..default()
+                        },
+                        BeaconBeam,
+                    ));
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(beam_mesh(
+                                &sections,
+                                BEAM_GLOW_RADIUS,
+                                BEAM_GLOW_ALPHA,
+                            )),
+                            material: effect_materials.beam_glow.clone(),
+                            ..default()
+                        },
+                        BeaconBeam,
+                    ));
+                });
+            }
+            "end_portal" | "end_gateway" => {
+                let material = if name == "end_portal" {
+                    effect_materials.end_portal.clone()
+                } else {
+                    effect_materials.end_gateway.clone()
+                };
+                commands.entity(entity).with_children(|parent| {
+                    parent.spawn(MaterialMeshBundle {
+                        mesh: meshes.add(portal_mesh(&block_world, pos, name)),
+                        material,
+                        ..default()
+                    });
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct BlockEffectPlugin;
+
+impl Plugin for BlockEffectPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            END_PORTAL_SHADER_HANDLE,
+            "end_portal.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<EndPortalMaterial>::default())
+            .add_systems(
+                Update,
+                spawn_block_effects
+                    .after(init_blocks)
+                    .run_if(in_state(AppLoadState::Finished)),
+            );
+    }
+}
@@ -0,0 +1,57 @@
+// Parallax starfield of end portals and end gateways, following the vanilla
+// `rendertype_end_portal` shader. The layers are projected in screen space, so the stars
+// stay put while the surface moves across the screen.
+
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::{view, globals},
+}
+
+struct EndPortalSettings {
+    layers: u32,
+}
+
+@group(2) @binding(0) var<uniform> settings: EndPortalSettings;
+@group(2) @binding(1) var sky_texture: texture_2d<f32>;
+@group(2) @binding(2) var sky_sampler: sampler;
+@group(2) @binding(3) var portal_texture: texture_2d<f32>;
+@group(2) @binding(4) var portal_sampler: sampler;
+
+var<private> COLORS: array<vec3<f32>, 16> = array<vec3<f32>, 16>(
+    vec3(0.022087, 0.098399, 0.110818),
+    vec3(0.011892, 0.095924, 0.089485),
+    vec3(0.027636, 0.101689, 0.100326),
+    vec3(0.046564, 0.109883, 0.114838),
+    vec3(0.064901, 0.117696, 0.097189),
+    vec3(0.063761, 0.086895, 0.123646),
+    vec3(0.084817, 0.111994, 0.166380),
+    vec3(0.097489, 0.154120, 0.091064),
+    vec3(0.106152, 0.131144, 0.195191),
+    vec3(0.097721, 0.110188, 0.187229),
+    vec3(0.133516, 0.138278, 0.148582),
+    vec3(0.070006, 0.243332, 0.235792),
+    vec3(0.196766, 0.142899, 0.214696),
+    vec3(0.047281, 0.315338, 0.321970),
+    vec3(0.204675, 0.390010, 0.302066),
+    vec3(0.080955, 0.314821, 0.661491),
+);
+
+fn layer_uv(uv: vec2<f32>, layer: f32) -> vec2<f32> {
+    // Vanilla's game time goes from 0 to 1 over a day of 20 minutes
+    let game_time = globals.time / 1200.0;
+    let translated = uv * 0.5 + 0.25 + vec2(17.0 / layer, (2.0 + layer / 1.5) * game_time * 1.5);
+    let angle = radians((layer * layer * 4321.0 + layer * 9.0) * 2.0);
+    let rotate = mat2x2(cos(angle), sin(angle), -sin(angle), cos(angle));
+    return (4.5 - layer / 4.0) * 2.0 * (rotate * translated);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = (in.position.xy - view.viewport.xy) / view.viewport.zw;
+    var color = textureSample(sky_texture, sky_sampler, uv).rgb * COLORS[0];
+    for (var i = 0u; i < settings.layers; i++) {
+        let layer_color = textureSample(portal_texture, portal_sampler, layer_uv(uv, f32(i + 1u)));
+        color += layer_color.rgb * COLORS[i];
+    }
+    return vec4(color, 1.0);
+}
@@ -0,0 +1,538 @@
+//! Blocks which vanilla draws with a block entity renderer instead of a block model. Their
+//! geometry is hardcoded here, following the vanilla model layer definitions and renderer
+//! transforms so that the `entity/...` textures line up.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use super::{parse_props, split_block_state};
+use crate::model::{part_rotation, y_rot, Cube, EntityModel, Part};
+
+/// Rotation around the vertical axis through the center of the block
+fn centered_y_rotation(degrees: f32) -> Mat4 {
+    Mat4::from_translation(Vec3::splat(8.0))
+        * Mat4::from_rotation_y(degrees.to_radians())
+        * Mat4::from_translation(Vec3::splat(-8.0))
+}
+
+fn chest_model(name: &str, props: &HashMap<&str, &str>) -> EntityModel {
+    let texture = match name {
+        "trapped_chest" => "trapped",
+        "ender_chest" => "ender",
+        _ => "normal",
+    };
+    // Each half of a double chest extends towards the other half, which is on the left for
+    // `type=left` and on the right for `type=right` when looking at the front
+    let (suffix, bottom, lid, lock) = match props.get("type").copied() {
+        Some("left") => (
+            "_left",
+            Cube::new(0.0, 19.0, [0.0, 0.0, 1.0], [15.0, 10.0, 14.0]),
+            Cube::new(0.0, 0.0, [0.0, 0.0, 0.0], [15.0, 5.0, 14.0]),
+            Cube::new(0.0, 0.0, [0.0, -2.0, 14.0], [1.0, 4.0, 1.0]),
+        ),
+        Some("right") => (
+            "_right",
+            Cube::new(0.0, 19.0, [1.0, 0.0, 1.0], [15.0, 10.0, 14.0]),
+            Cube::new(0.0, 0.0, [1.0, 0.0, 0.0], [15.0, 5.0, 14.0]),
+            Cube::new(0.0, 0.0, [15.0, -2.0, 14.0], [1.0, 4.0, 1.0]),
+        ),
+        _ => (
+            "",
+            Cube::new(0.0, 19.0, [1.0, 0.0, 1.0], [14.0, 10.0, 14.0]),
+            Cube::new(0.0, 0.0, [1.0, 0.0, 0.0], [14.0, 5.0, 14.0]),
+            Cube::new(0.0, 0.0, [7.0, -2.0, 14.0], [2.0, 4.0, 1.0]),
+        ),
+    };
+
+    let facing = centered_y_rotation(-y_rot(props.get("facing").copied().unwrap_or("south")));
+    EntityModel {
+        texture: format!("entity/chest/{}{}", texture, suffix),
+        parts: vec![
+            Part {
+                transform: facing,
+                cubes: vec![bottom],
+            },
+            Part {
+                transform: facing * Mat4::from_translation(Vec3::new(0.0, 9.0, 1.0)),
+                cubes: vec![lid, lock],
+            },
+        ],
+    }
+}
+
+fn bed_model(color: &str, props: &HashMap<&str, &str>) -> EntityModel {
+    let facing = y_rot(props.get("facing").copied().unwrap_or("south"));
+    // Bed models are modelled standing upright, so they get tipped over onto their back
+    let transform = Mat4::from_translation(Vec3::new(0.0, 9.0, 0.0))
+        * Mat4::from_rotation_x(PI / 2.0)
+        * Mat4::from_translation(Vec3::splat(8.0))
+        * Mat4::from_rotation_z((180.0 + facing).to_radians())
+        * Mat4::from_translation(Vec3::splat(-8.0));
+
+    let (main, (left_leg, left_leg_rot), (right_leg, right_leg_rot)) =
+        if props.get("part").copied() == Some("foot") {
+            (
+                Cube::new(0.0, 22.0, [0.0, 0.0, 0.0], [16.0, 16.0, 6.0]),
+                (
+                    Cube::new(50.0, 0.0, [0.0, 6.0, -16.0], [3.0, 3.0, 3.0]),
+                    part_rotation(PI / 2.0, 0.0, 0.0),
+                ),
+                (
+                    Cube::new(50.0, 12.0, [-16.0, 6.0, -16.0], [3.0, 3.0, 3.0]),
+                    part_rotation(PI / 2.0, 0.0, 3.0 * PI / 2.0),
+                ),
+            )
+        } else {
+            (
+                Cube::new(0.0, 0.0, [0.0, 0.0, 0.0], [16.0, 16.0, 6.0]),
+                (
+                    Cube::new(50.0, 6.0, [0.0, 6.0, 0.0], [3.0, 3.0, 3.0]),
+                    part_rotation(PI / 2.0, 0.0, PI / 2.0),
+                ),
+                (
+                    Cube::new(50.0, 18.0, [-16.0, 6.0, 0.0], [3.0, 3.0, 3.0]),
+                    part_rotation(PI / 2.0, 0.0, PI),
+                ),
+            )
+        };
+
+    EntityModel {
+        texture: format!("entity/bed/{}", color),
+        parts: vec![
+            Part {
+                transform,
+                cubes: vec![main],
+            },
+            Part {
+                transform: transform * left_leg_rot,
+                cubes: vec![left_leg],
+            },
+            Part {
+                transform: transform * right_leg_rot,
+                cubes: vec![right_leg],
+            },
+        ],
+    }
+}
+
+fn shulker_box_model(color: Option<&str>, props: &HashMap<&str, &str>) -> EntityModel {
+    let texture = match color {
+        Some(color) => format!("entity/shulker/shulker_{}", color),
+        None => "entity/shulker/shulker".to_string(),
+    };
+    let rotation = match props.get("facing").copied() {
+        Some("down") => Quat::from_rotation_x(PI),
+        Some("north") => Quat::from_euler(EulerRot::XYZ, PI / 2.0, 0.0, PI),
+        Some("south") => Quat::from_rotation_x(PI / 2.0),
+        Some("west") => Quat::from_euler(EulerRot::XYZ, PI / 2.0, 0.0, PI / 2.0),
+        Some("east") => Quat::from_euler(EulerRot::XYZ, PI / 2.0, 0.0, -PI / 2.0),
+        _ => Quat::IDENTITY,
+    };
+    // The slight downscale keeps the box from z-fighting with neighbouring blocks
+    let transform = Mat4::from_translation(Vec3::splat(8.0))
+        * Mat4::from_scale(Vec3::splat(0.9995))
+        * Mat4::from_quat(rotation)
+        * Mat4::from_scale(Vec3::new(1.0, -1.0, -1.0))
+        * Mat4::from_translation(Vec3::new(0.0, 8.0, 0.0));
+
+    EntityModel {
+        texture,
+        parts: vec![Part {
+            transform,
+            cubes: vec![
+                Cube::new(0.0, 0.0, [-8.0, -16.0, -8.0], [16.0, 12.0, 16.0]),
+                Cube::new(0.0, 28.0, [-8.0, -8.0, -8.0], [16.0, 8.0, 16.0]),
+            ],
+        }],
+    }
+}
+
+fn bell_model() -> EntityModel {
+    EntityModel {
+        texture: "entity/bell/bell_body".to_string(),
+        parts: vec![Part {
+            transform: Mat4::IDENTITY,
+            cubes: vec![
+                Cube::new(0.0, 0.0, [5.0, 6.0, 5.0], [6.0, 7.0, 6.0]),
+                Cube::new(0.0, 13.0, [4.0, 4.0, 4.0], [8.0, 2.0, 8.0]),
+            ],
+        }],
+    }
+}
+
+fn skull_model(kind: &str, wall: bool, props: &HashMap<&str, &str>) -> Option<EntityModel> {
+    let head = || Cube::new(0.0, 0.0, [-4.0, -8.0, -4.0], [8.0, 8.0, 8.0]);
+    let hat = || Cube::new(32.0, 0.0, [-4.0, -8.0, -4.0], [8.0, 8.0, 8.0]).grow(0.25);
+    let (texture, cubes, extra_parts) = match kind {
+        "skeleton" => ("entity/skeleton/skeleton", vec![head()], Vec::new()),
+        "wither_skeleton" => ("entity/skeleton/wither_skeleton", vec![head()], Vec::new()),
+        "creeper" => ("entity/creeper/creeper", vec![head()], Vec::new()),
+        "zombie" => ("entity/zombie/zombie", vec![head(), hat()], Vec::new()),
+        "player" => ("entity/player/wide/steve", vec![head(), hat()], Vec::new()),
+        "piglin" => (
+            "entity/piglin/piglin",
+            vec![
+                Cube::new(0.0, 0.0, [-5.0, -8.0, -4.0], [10.0, 8.0, 8.0]),
+                Cube::new(31.0, 1.0, [-2.0, -4.0, -5.0], [4.0, 4.0, 1.0]),
+                Cube::new(2.0, 4.0, [2.0, -2.0, -5.0], [1.0, 2.0, 1.0]),
+                Cube::new(2.0, 0.0, [-3.0, -2.0, -5.0], [1.0, 2.0, 1.0]),
+            ],
+            vec![
+                (
+                    Mat4::from_translation(Vec3::new(4.5, -6.0, 0.0))
+                        * part_rotation(0.0, 0.0, -PI / 6.0),
+                    Cube::new(51.0, 6.0, [0.0, 0.0, -2.0], [1.0, 5.0, 4.0]),
+                ),
+                (
+                    Mat4::from_translation(Vec3::new(-4.5, -6.0, 0.0))
+                        * part_rotation(0.0, 0.0, PI / 6.0),
+                    Cube::new(39.0, 6.0, [-1.0, 0.0, -2.0], [1.0, 5.0, 4.0]),
+                ),
+            ],
+        ),
+        // The dragon head uses a much more involved model which isn't supported yet
+        _ => return None,
+    };
+
+    let (translation, y_rot) = if wall {
+        let facing = props.get("facing").copied().unwrap_or("north");
+        let step = match facing {
+            "north" => Vec2::new(0.0, -1.0),
+            "south" => Vec2::new(0.0, 1.0),
+            "west" => Vec2::new(-1.0, 0.0),
+            _ => Vec2::new(1.0, 0.0),
+        };
+        let translation = Vec3::new(8.0 - step.x * 4.0, 4.0, 8.0 - step.y * 4.0);
+        // The skull faces away from the wall it's attached to
+        (translation, y_rot(facing) + 180.0)
+    } else {
+        let rotation: f32 = props
+            .get("rotation")
+            .and_then(|rotation| rotation.parse().ok())
+            .unwrap_or_default();
+        (Vec3::new(8.0, 0.0, 8.0), rotation * 22.5)
+    };
+    let transform = Mat4::from_translation(translation)
+        * Mat4::from_scale(Vec3::new(-1.0, -1.0, 1.0))
+        * Mat4::from_rotation_y(y_rot.to_radians());
+
+    let mut parts = vec![Part { transform, cubes }];
+    for (part_transform, cube) in extra_parts {
+        parts.push(Part {
+            transform: transform * part_transform,
+            cubes: vec![cube],
+        });
+    }
+    Some(EntityModel {
+        texture: texture.to_string(),
+        parts,
+    })
+}
+
+/// The open book on a lectern, in the pose vanilla's `LecternRenderer` gives it
+fn lectern_book_model(props: &HashMap<&str, &str>) -> EntityModel {
+    // How far the book is opened, from `BookModel::setupAnim` at time 0
+    let open = 1.25 * 1.2;
+    let part = |x: f32, z: f32, y_rot: f32, cube| Part {
+        transform: Mat4::from_translation(Vec3::new(x, 0.0, z)) * part_rotation(0.0, y_rot, 0.0),
+        cubes: vec![cube],
+    };
+    let page_x = open.sin();
+    let page = || Cube::new(24.0, 10.0, [0.0, -4.0, 0.0], [5.0, 8.0, 0.005]);
+    let parts = vec![
+        part(
+            0.0,
+            -1.0,
+            PI + open,
+            Cube::new(0.0, 0.0, [-6.0, -5.0, -0.005], [6.0, 10.0, 0.005]),
+        ),
+        part(
+            0.0,
+            1.0,
+            -open,
+            Cube::new(16.0, 0.0, [0.0, -5.0, -0.005], [6.0, 10.0, 0.005]),
+        ),
+        part(
+            0.0,
+            0.0,
+            PI / 2.0,
+            Cube::new(12.0, 0.0, [-1.0, -5.0, 0.0], [2.0, 10.0, 0.005]),
+        ),
+        part(
+            page_x,
+            0.0,
+            open,
+            Cube::new(0.0, 10.0, [0.0, -4.0, -0.99], [5.0, 8.0, 1.0]),
+        ),
+        part(
+            page_x,
+            0.0,
+            -open,
+            Cube::new(12.0, 10.0, [0.0, -4.0, -0.01], [5.0, 8.0, 1.0]),
+        ),
+        part(page_x, 0.0, open - open * 2.0 * 0.1, page()),
+        part(page_x, 0.0, open - open * 2.0 * 0.9, page()),
+    ];
+
+    // The book is turned towards the clockwise neighbour of the facing direction
+    let facing = match props.get("facing").copied().unwrap_or("north") {
+        "north" => "east",
+        "east" => "south",
+        "south" => "west",
+        _ => "north",
+    };
+    let model = EntityModel {
+        texture: "entity/enchanting_table_book".to_string(),
+        parts,
+    };
+    model.transformed(
+        Mat4::from_translation(Vec3::new(8.0, 17.0, 8.0))
+            * Mat4::from_rotation_y((-y_rot(facing)).to_radians())
+            * Mat4::from_rotation_z(67.5f32.to_radians())
+            * Mat4::from_translation(Vec3::new(0.0, -2.0, 0.0)),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SignKind {
+    Standing,
+    Wall,
+    Hanging,
+    WallHanging,
+}
+
+fn sign_kind(name: &str) -> Option<(&str, SignKind)> {
+    for (suffix, kind) in [
+        ("_wall_hanging_sign", SignKind::WallHanging),
+        ("_hanging_sign", SignKind::Hanging),
+        ("_wall_sign", SignKind::Wall),
+        ("_sign", SignKind::Standing),
+    ] {
+        if let Some(wood) = name.strip_suffix(suffix) {
+            return Some((wood, kind));
+        }
+    }
+    None
+}
+
+/// The transform shared by the sign model and its text
+fn sign_transform(kind: SignKind, props: &HashMap<&str, &str>) -> Mat4 {
+    let rotation = || -> f32 {
+        let rotation: f32 = props
+            .get("rotation")
+            .and_then(|rotation| rotation.parse().ok())
+            .unwrap_or_default();
+        -rotation * 22.5
+    };
+    let facing = || -y_rot(props.get("facing").copied().unwrap_or("north"));
+    match kind {
+        SignKind::Standing => {
+            Mat4::from_translation(Vec3::splat(8.0))
+                * Mat4::from_rotation_y(rotation().to_radians())
+        }
+        SignKind::Wall => {
+            Mat4::from_translation(Vec3::splat(8.0))
+                * Mat4::from_rotation_y(facing().to_radians())
+                * Mat4::from_translation(Vec3::new(0.0, -5.0, -7.0))
+        }
+        SignKind::Hanging | SignKind::WallHanging => {
+            let y_rot = if kind == SignKind::Hanging {
+                rotation()
+            } else {
+                facing()
+            };
+            Mat4::from_translation(Vec3::new(8.0, 15.0, 8.0))
+                * Mat4::from_rotation_y(y_rot.to_radians())
+                * Mat4::from_translation(Vec3::new(0.0, -5.0, 0.0))
+        }
+    }
+}
+
+fn sign_model(wood: &str, kind: SignKind, props: &HashMap<&str, &str>) -> EntityModel {
+    let transform = sign_transform(kind, props);
+    if matches!(kind, SignKind::Standing | SignKind::Wall) {
+        let mut cubes = vec![Cube::new(0.0, 0.0, [-12.0, -14.0, -1.0], [24.0, 12.0, 2.0])];
+        if kind == SignKind::Standing {
+            cubes.push(Cube::new(0.0, 14.0, [-1.0, -2.0, -1.0], [2.0, 14.0, 2.0]));
+        }
+        return EntityModel {
+            texture: format!("entity/signs/{}", wood),
+            parts: vec![Part {
+                transform: transform * Mat4::from_scale(Vec3::new(2.0, -2.0, -2.0) / 3.0),
+                cubes,
+            }],
+        };
+    }
+
+    let transform = transform * Mat4::from_scale(Vec3::new(1.0, -1.0, -1.0));
+    let mut parts = vec![Part {
+        transform,
+        cubes: vec![Cube::new(0.0, 12.0, [-7.0, 0.0, -1.0], [14.0, 10.0, 2.0])],
+    }];
+    let v_chains = || Cube::new(14.0, 6.0, [-6.0, -6.0, 0.0], [12.0, 6.0, 0.0]);
+    if kind == SignKind::WallHanging {
+        parts.push(Part {
+            transform,
+            cubes: vec![
+                Cube::new(0.0, 0.0, [-8.0, -6.0, -2.0], [16.0, 2.0, 4.0]),
+                v_chains(),
+            ],
+        });
+    } else if props.get("attached").copied() == Some("true") {
+        parts.push(Part {
+            transform,
+            cubes: vec![v_chains()],
+        });
+    } else {
+        // Two crossed chains on either side
+        for (x, u, y_rot) in [
+            (-5.0, 0.0, -PI / 4.0),
+            (-5.0, 6.0, PI / 4.0),
+            (5.0, 0.0, -PI / 4.0),
+            (5.0, 6.0, PI / 4.0),
+        ] {
+            parts.push(Part {
+                transform: transform
+                    * Mat4::from_translation(Vec3::new(x, -6.0, 0.0))
+                    * part_rotation(0.0, y_rot, 0.0),
+                cubes: vec![Cube::new(u, 6.0, [-1.5, 0.0, 0.0], [3.0, 6.0, 0.0])],
+            });
+        }
+    }
+
+    EntityModel {
+        texture: format!("entity/signs/hanging/{}", wood),
+        parts,
+    }
+}
+
+/// Where the text on each side of a sign goes
+pub struct SignTextLayout {
+    /// Transform from text space to block pixels for the front of the sign. Text space is in
+    /// font pixels, with the origin at the center of the text and Y pointing down.
+    pub front: Mat4,
+    /// Same as `front`, but for the back of the sign
+    pub back: Mat4,
+    pub line_height: f32,
+    pub max_line_width: f32,
+}
+
+pub fn sign_text_layout(block: &str) -> Option<SignTextLayout> {
+    let (name, props) = split_block_state(block);
+    let (_, kind) = sign_kind(name.trim_start_matches("minecraft:"))?;
+    let transform = sign_transform(kind, &parse_props(props));
+
+    let (offset, scale, line_height, max_line_width) = match kind {
+        SignKind::Standing | SignKind::Wall => {
+            (Vec3::new(0.0, 16.0 / 3.0, 0.7467), 1.0 / 6.0, 10.0, 90.0)
+        }
+        SignKind::Hanging | SignKind::WallHanging => {
+            (Vec3::new(0.0, -5.12, 1.168), 0.225, 9.0, 60.0)
+        }
+    };
+    let text = Mat4::from_translation(offset) * Mat4::from_scale(Vec3::new(scale, -scale, scale));
+    Some(SignTextLayout {
+        front: transform * text,
+        back: transform * Mat4::from_rotation_y(PI) * text,
+        line_height,
+        max_line_width,
+    })
+}
+
+/// Returns the model of a banner, textured with the 64x64 composite of `entity/banner_base`
+/// and the banner's pattern layers. Banners aren't part of the block mesh since every pattern
+/// stack needs a texture of its own.
+pub fn banner_model(block: &str) -> Option<EntityModel> {
+    let (name, props) = split_block_state(block);
+    let name = name.trim_start_matches("minecraft:");
+    let props = parse_props(props);
+
+    let standing = if name.ends_with("_wall_banner") {
+        false
+    } else if name.ends_with("_banner") {
+        true
+    } else {
+        return None;
+    };
+
+    // The flag waves in vanilla, this is its pose at the start of the animation
+    let flag = Part {
+        transform: Mat4::from_translation(Vec3::new(0.0, -32.0, 0.0))
+            * part_rotation(-0.0025 * PI, 0.0, 0.0),
+        cubes: vec![Cube::new(0.0, 0.0, [-10.0, 0.0, -2.0], [20.0, 40.0, 1.0])],
+    };
+    let bar = Part {
+        transform: Mat4::IDENTITY,
+        cubes: vec![Cube::new(0.0, 42.0, [-10.0, -32.0, -1.0], [20.0, 2.0, 2.0])],
+    };
+    let mut parts = vec![flag, bar];
+
+    let transform = if standing {
+        parts.push(Part {
+            transform: Mat4::IDENTITY,
+            cubes: vec![Cube::new(44.0, 0.0, [-1.0, -30.0, -1.0], [2.0, 42.0, 2.0])],
+        });
+        let rotation = props
+            .get("rotation")
+            .and_then(|rotation| rotation.parse::<f32>().ok())
+            .unwrap_or_default();
+        Mat4::from_translation(Vec3::splat(8.0))
+            * Mat4::from_rotation_y((-rotation * 22.5).to_radians())
+    } else {
+        let facing = props.get("facing").copied().unwrap_or("north");
+        Mat4::from_translation(Vec3::new(8.0, -8.0 / 3.0, 8.0))
+            * Mat4::from_rotation_y((-y_rot(facing)).to_radians())
+            * Mat4::from_translation(Vec3::new(0.0, -5.0, -7.0))
+    };
+    let model = EntityModel {
+        texture: "entity/banner_base".to_string(),
+        parts,
+    };
+    Some(
+        model.transformed(
+            transform * Mat4::from_scale(Vec3::new(2.0 / 3.0, -2.0 / 3.0, -2.0 / 3.0)),
+        ),
+    )
+}
+
+/// Returns the hardcoded model for blocks which are drawn by a block entity renderer
+pub fn block_entity_model(block: &str) -> Option<EntityModel> {
+    let (name, props) = split_block_state(block);
+    let name = name.trim_start_matches("minecraft:");
+    let props = parse_props(props);
+
+    if matches!(name, "chest" | "trapped_chest" | "ender_chest") {
+        return Some(chest_model(name, &props));
+    }
+    if name == "bell" {
+        return Some(bell_model());
+    }
+    if name == "lectern" && props.get("has_book") == Some(&"true") {
+        return Some(lectern_book_model(&props));
+    }
+    if name == "shulker_box" {
+        return Some(shulker_box_model(None, &props));
+    }
+    if let Some(color) = name.strip_suffix("_shulker_box") {
+        return Some(shulker_box_model(Some(color), &props));
+    }
+    if let Some((wood, kind)) = sign_kind(name) {
+        return Some(sign_model(wood, kind, &props));
+    }
+    if let Some(color) = name.strip_suffix("_bed") {
+        return Some(bed_model(color, &props));
+    }
+    for (suffix, wall) in [
+        ("_wall_skull", true),
+        ("_wall_head", true),
+        ("_skull", false),
+        ("_head", false),
+    ] {
+        if let Some(kind) = name.strip_suffix(suffix) {
+            return skull_model(kind, wall, &props);
+        }
+    }
+    None
+}
@@ -0,0 +1,93 @@
+//! Changes to how blocks are drawn made by the viewing tools, like ghosting by the x-ray and the
+//! colors of the property view. Blocks are given a variant of their own material, which is
+//! shared by every block with the same material and look.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::{init_blocks, Block, BlockMaterial};
+use crate::AppLoadState;
+
+/// How much darker dimmed blocks are drawn
+const DIMMED_BRIGHTNESS: f32 = 0.2;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct BlockLook {
+    /// Replaces the color the block's texture is multiplied with
+    pub tint: Option<Color>,
+    pub dimmed: bool,
+    pub ghost: bool,
+}
+
+/// Material the block had before its look was changed
+#[derive(Component)]
+pub(super) struct OwnMaterial(Handle<BlockMaterial>);
+
+type VariantKey = (AssetId<BlockMaterial>, Option<u32>, bool, bool);
+
+fn apply_block_looks(
+    mut commands: Commands,
+    mut variants: Local<HashMap<VariantKey, Handle<BlockMaterial>>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
+    mut blocks: Query<
+        (
+            Entity,
+            &BlockLook,
+            &mut Handle<BlockMaterial>,
+            Option<&OwnMaterial>,
+        ),
+        Or<(Changed<BlockLook>, Changed<Block>)>,
+    >,
+) {
+    for (entity, look, mut material, own) in blocks.iter_mut() {
+        let own = own.map_or_else(|| material.clone(), |own| own.0.clone());
+        if *look == BlockLook::default() {
+            if *material != own {
+                *material = own;
+                commands.entity(entity).remove::<OwnMaterial>();
+            }
+            continue;
+        }
+
+        let tint = look.tint.map(|tint| LinearRgba::from(tint).as_u32());
+        let key = (own.id(), tint, look.dimmed, look.ghost);
+        let variant = match variants.get(&key) {
+            Some(variant) => variant.clone(),
+            None => {
+                // Blocks that haven't been given their material yet are left alone
+                let Some(mut variant) = materials.get(&own).cloned() else {
+                    continue;
+                };
+                if let Some(tint) = look.tint {
+                    variant.base.base_color = tint;
+                }
+                if look.dimmed {
+                    let color = variant.base.base_color.to_linear();
+                    variant.base.base_color =
+                        (color * DIMMED_BRIGHTNESS).with_alpha(color.alpha).into();
+                }
+                variant.extension.ghost_all = look.ghost.into();
+                let variant = materials.add(variant);
+                variants.insert(key, variant.clone());
+                variant
+            }
+        };
+        if *material == own {
+            commands.entity(entity).insert(OwnMaterial(own));
+        }
+        *material = variant;
+    }
+}
+
+pub struct BlockLookPlugin;
+
+impl Plugin for BlockLookPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_block_looks
+                .after(init_blocks)
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
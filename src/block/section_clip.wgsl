@@ -0,0 +1,102 @@
+// Block material that discards blocks outside of the section box and dithers ghosted blocks,
+// in both the main pass and the prepasses so cut away blocks don't leave depth or shadows
+// behind. The schematic root sits at the world origin, so world positions are block positions.
+
+#import bevy_pbr::mesh_functions::get_world_from_local
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_prepass_functions,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct SectionClip {
+    min: vec3<f32>,
+    max: vec3<f32>,
+    ghost_min: vec3<f32>,
+    ghost_max: vec3<f32>,
+    ghost_all: u32,
+}
+
+@group(2) @binding(100) var<uniform> clip: SectionClip;
+
+// Share of the pixels of a ghosted block that are still drawn
+const GHOST_COVERAGE: f32 = 0.3;
+
+var<private> BAYER: array<f32, 16> = array<f32, 16>(
+    0.0 / 16.0, 8.0 / 16.0, 2.0 / 16.0, 10.0 / 16.0,
+    12.0 / 16.0, 4.0 / 16.0, 14.0 / 16.0, 6.0 / 16.0,
+    3.0 / 16.0, 11.0 / 16.0, 1.0 / 16.0, 9.0 / 16.0,
+    15.0 / 16.0, 7.0 / 16.0, 13.0 / 16.0, 5.0 / 16.0,
+);
+
+fn clip_block(instance_index: u32, frag_coord: vec2<f32>) {
+    // Block meshes have their origin at the corner of the block
+    let origin = get_world_from_local(instance_index)[3].xyz;
+    let block = floor(origin + vec3(0.001));
+    if any(block < clip.min) || any(block >= clip.max) {
+        discard;
+    }
+
+    let ghosted = clip.ghost_all != 0u
+        || (all(block >= clip.ghost_min) && all(block < clip.ghost_max));
+    let pixel = vec2<u32>(frag_coord) % 4u;
+    if ghosted && BAYER[pixel.y * 4u + pixel.x] >= GHOST_COVERAGE {
+        discard;
+    }
+}
+
+#ifdef PREPASS_PIPELINE
+
+#ifdef PREPASS_FRAGMENT
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    clip_block(in.instance_index, in.position.xy);
+    pbr_prepass_functions::prepass_alpha_discard(in);
+
+    var out: FragmentOutput;
+#ifdef DEPTH_CLAMP_ORTHO
+    out.frag_depth = in.clip_position_unclamped.z;
+#endif
+#ifdef NORMAL_PREPASS
+    out.normal = vec4(in.world_normal * 0.5 + vec3(0.5), 1.0);
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    out.motion_vector = pbr_prepass_functions::calculate_motion_vector(
+        in.world_position,
+        in.previous_world_position,
+    );
+#endif
+    return out;
+}
+#else
+@fragment
+fn fragment(in: VertexOutput) {
+    clip_block(in.instance_index, in.position.xy);
+    pbr_prepass_functions::prepass_alpha_discard(in);
+}
+#endif
+
+#else
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    clip_block(in.instance_index, in.position.xy);
+
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
+
+#endif
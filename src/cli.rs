@@ -2,6 +2,10 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::environment::Dimension;
+use crate::origin::CoordinateSpace;
+use crate::transform::{MirrorAxis, Rotation};
+
 #[derive(Parser)]
 pub struct Cli {
     #[arg(long, value_name = "SCHEMATIC_FILE")]
@@ -10,6 +14,49 @@ pub struct Cli {
     pub no_vsync: bool,
     #[arg(long)]
     pub wireframe: bool,
+    /// Time of day in ticks, where 0 is sunrise, 6000 is noon and 18000 is midnight
+    #[arg(long, default_value_t = 6000.0)]
+    pub time: f32,
+    /// Advance the time of day automatically
+    #[arg(long)]
+    pub day_cycle: bool,
+    /// Number of shadow cascades, defaults to a value based on the schematic size
+    #[arg(long)]
+    pub shadow_cascades: Option<usize>,
+    /// Maximum distance from the camera that receives shadows
+    #[arg(long)]
+    pub shadow_distance: Option<f32>,
+    /// Shadow depth bias, increase this if shadow acne appears
+    #[arg(long)]
+    pub shadow_bias: Option<f32>,
+    /// Dimension preset controlling the sky, fog and ambient light
+    #[arg(long, value_enum, default_value_t)]
+    pub dimension: Dimension,
+    /// Whether coordinates are shown in the world or relative to the WorldEdit origin
+    #[arg(long, value_enum, default_value_t)]
+    pub coordinates: CoordinateSpace,
+    /// File the x-ray presets are saved to
+    #[arg(long, value_name = "PRESETS_FILE", default_value = "xray_presets.json")]
+    pub xray_presets: PathBuf,
+    /// Print the blocks matching a query like `minecraft:repeater[delay=4]` and exit without
+    /// opening a window
+    #[arg(long)]
+    pub query: Option<String>,
+    /// Unpacked data pack to read block tags from, can be given more than once
+    #[arg(long = "datapack", value_name = "DATAPACK_DIR")]
+    pub datapacks: Vec<PathBuf>,
+    /// Export the material list to a .csv or .json file and exit without opening a window
+    #[arg(long, value_name = "FILE")]
+    pub export_materials: Option<PathBuf>,
+    /// Language block and item names are shown in, like en_us or de_de
+    #[arg(long, default_value = "en_us")]
+    pub locale: String,
+    /// Turn the schematic clockwise, seen from above, by this many degrees when loading it
+    #[arg(long, value_enum, default_value_t)]
+    pub rotate: Rotation,
+    /// Mirror the schematic along an axis when loading it, before turning it
+    #[arg(long, value_enum)]
+    pub mirror: Option<MirrorAxis>,
 }
 
 pub fn parse() -> Cli {
@@ -1,36 +1,293 @@
+//! Debug overlay in the style of Minecraft's F3 screen, showing the camera position and the
+//! targeted block in Minecraft's own terms, and a window with the viewer settings.
+
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use bevy_fly_camera::FlyCamera;
+
+use crate::block::{split_block_state, BlockModels, BlockPalette};
+use crate::environment::Dimension;
+use crate::lang::Lang;
+use crate::light::LightEstimator;
+use crate::origin::{CoordinateSpace, SchematicMarkers};
+use crate::picking::TargetedBlock;
+use crate::section::{section_settings, SectionView};
+use crate::time_of_day::{TimeOfDay, TICKS_PER_DAY};
+use crate::{BlockWorld, McCamera, SchematicRoot};
+
+/// Horizontal directions in the order of vanilla's yaw, which starts at south and turns west
+const FACINGS: [(&str, &str); 4] = [
+    ("south", "Towards positive Z"),
+    ("west", "Towards negative X"),
+    ("north", "Towards negative Z"),
+    ("east", "Towards positive X"),
+];
+
+/// Whether the overlay is shown, toggled with F3
+#[derive(Resource)]
+struct DebugOverlay {
+    visible: bool,
+}
+
+/// Last position light was estimated at, with the sky and block light found there
+#[derive(Default)]
+struct LightCache {
+    estimator: LightEstimator,
+    last: Option<(IVec3, (u8, u8))>,
+}
+
+/// Yaw and pitch in degrees the way Minecraft measures them, for a direction in schematic space
+fn yaw_pitch(dir: Vec3) -> (f32, f32) {
+    let yaw = (-dir.x).atan2(dir.z).to_degrees();
+    let pitch = (-dir.y).clamp(-1.0, 1.0).asin().to_degrees();
+    (yaw, pitch)
+}
+
+fn facing(yaw: f32) -> (&'static str, &'static str) {
+    FACINGS[((yaw / 90.0 + 0.5).floor() as i32).rem_euclid(4) as usize]
+}
+
+/// Lines about the camera, like the left side of the F3 screen
+fn position_lines(
+    local: Vec3,
+    dir: Vec3,
+    space: CoordinateSpace,
+    block_world: &BlockWorld,
+    block_models: &BlockModels,
+    has_sky: bool,
+    light_cache: &mut LightCache,
+) -> Vec<String> {
+    let shown = local + space.offset(block_world).as_vec3();
+    let block = shown.floor().as_ivec3();
+    let local_block = local.floor().as_ivec3();
+    // Chunks only line up with the world
+    let world_block = local_block + block_world.offset;
+    let in_chunk = world_block.rem_euclid(IVec3::splat(16));
+    let chunk = world_block.div_euclid(IVec3::splat(16));
+    let (yaw, pitch) = yaw_pitch(dir);
+    let (facing, towards) = facing(yaw);
 
-use crate::McCamera;
+    let (sky, light) = match light_cache.last {
+        Some((pos, light)) if pos == local_block => light,
+        _ => {
+            let light =
+                light_cache
+                    .estimator
+                    .light_at(block_world, block_models, has_sky, local_block);
+            light_cache.last = Some((local_block, light));
+            light
+        }
+    };
+    let biome = block_world
+        .biomes
+        .as_ref()
+        .and_then(|biomes| biomes.get(local_block))
+        .unwrap_or("unknown");
 
-fn show_gui(mut contexts: EguiContexts, camera_query: Query<&Transform, With<McCamera>>) {
-    let camera_transform = camera_query.single();
-    let (yaw, pitch, _) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+    vec![
+        "MC Renderer".to_string(),
+        String::new(),
+        format!("Coordinates: {}", space.name()),
+        format!("XYZ: {:.3} / {:.3} / {:.3}", shown.x, shown.y, shown.z),
+        format!("Block: {} {} {}", block.x, block.y, block.z),
+        format!(
+            "Chunk: {} {} {} in {} {} {}",
+            in_chunk.x, in_chunk.y, in_chunk.z, chunk.x, chunk.y, chunk.z
+        ),
+        format!(
+            "Facing: {} ({}) ({:.1} / {:.1})",
+            facing, towards, yaw, pitch
+        ),
+        format!("Light: {} ({} sky, {} block)", sky.max(light), sky, light),
+        format!("Biome: {}", biome),
+    ]
+}
+
+/// Lines about the targeted block, like the right side of the F3 screen
+fn block_lines(
+    pos: IVec3,
+    space: CoordinateSpace,
+    block_world: &BlockWorld,
+    palette: &BlockPalette,
+    lang: &Lang,
+) -> Vec<String> {
+    let block = block_world
+        .blocks
+        .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+    let shown = pos + space.offset(block_world);
+    let (name, props) = split_block_state(block);
 
-    egui::Window::new("Debug Info").show(contexts.ctx_mut(), |ui| {
-        egui::Grid::new("debug_info_grid")
+    let mut lines = vec![
+        format!("Targeted Block: {}, {}, {}", shown.x, shown.y, shown.z),
+        name.to_string(),
+        lang.block_name(name),
+    ];
+    // Properties stay in the order of the block state rather than being sorted
+    lines.extend(
+        props
+            .split(',')
+            .filter_map(|prop| prop.split_once('='))
+            .map(|(key, value)| format!("{}: {}", key, value)),
+    );
+    if let Some(idx) = palette.get(block) {
+        lines.push(format!("Palette Index: {}", idx));
+    }
+    lines
+}
+
+fn overlay_column(ctx: &egui::Context, id: &str, anchor: egui::Align2, lines: &[String]) {
+    let align = if anchor == egui::Align2::RIGHT_TOP {
+        egui::Align::Max
+    } else {
+        egui::Align::Min
+    };
+    egui::Area::new(egui::Id::new(id))
+        .anchor(anchor, [0.0, 0.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::none()
+                .fill(egui::Color32::from_black_alpha(144))
+                .inner_margin(4.0)
+                .show(ui, |ui| {
+                    ui.with_layout(egui::Layout::top_down(align), |ui| {
+                        for line in lines {
+                            ui.label(
+                                egui::RichText::new(line)
+                                    .monospace()
+                                    .color(egui::Color32::WHITE),
+                            );
+                        }
+                    });
+                });
+        });
+}
+
+fn toggle_overlay(keyboard_input: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn show_gui(
+    mut contexts: EguiContexts,
+    mut light_cache: Local<LightCache>,
+    overlay: Res<DebugOverlay>,
+    mut space: ResMut<CoordinateSpace>,
+    mut markers: ResMut<SchematicMarkers>,
+    mut section: ResMut<SectionView>,
+    camera_query: Query<(&Transform, &FlyCamera), With<McCamera>>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    targeted: Res<TargetedBlock>,
+    block_world: Res<BlockWorld>,
+    block_models: Res<BlockModels>,
+    palette: Res<BlockPalette>,
+    lang: Res<Lang>,
+    dimension: Res<Dimension>,
+) {
+    // Edits change how far light reaches
+    if block_world.is_changed() {
+        *light_cache = LightCache::default();
+    }
+    let (camera_transform, fly_camera) = camera_query.single();
+    let block_lines = targeted
+        .0
+        .map(|pos| block_lines(pos, *space, &block_world, &palette, &lang));
+
+    if overlay.visible {
+        // The root isn't spawned until loading has finished
+        let to_schematic = root
+            .get_single()
+            .map_or(Mat4::IDENTITY, |root| root.compute_matrix().inverse());
+        let local = to_schematic.transform_point3(camera_transform.translation);
+        let dir = to_schematic.transform_vector3(*camera_transform.forward());
+        // Only the overworld has sky light
+        let lines = position_lines(
+            local,
+            dir,
+            *space,
+            &block_world,
+            &block_models,
+            dimension.has_sky_cycle(),
+            &mut light_cache,
+        );
+        let ctx = contexts.ctx_mut();
+        overlay_column(ctx, "debug_overlay_left", egui::Align2::LEFT_TOP, &lines);
+        if let Some(lines) = &block_lines {
+            overlay_column(ctx, "debug_overlay_right", egui::Align2::RIGHT_TOP, lines);
+        }
+    }
+
+    egui::Window::new("Settings").show(contexts.ctx_mut(), |ui| {
+        egui::Grid::new("settings_grid")
             .num_columns(2)
             .striped(true)
             .show(ui, |ui| {
-                ui.label("Camera Position");
-                ui.label(format!("{:?}", camera_transform.translation));
+                // Only write back on change to avoid triggering change detection every frame
+                ui.label("Time of Day");
+                let mut ticks = time_of_day.ticks;
+                if ui
+                    .add(egui::Slider::new(&mut ticks, 0.0..=TICKS_PER_DAY))
+                    .changed()
+                {
+                    time_of_day.ticks = ticks;
+                }
+                ui.end_row();
+
+                ui.label("Day Cycle");
+                let mut cycle = time_of_day.cycle;
+                if ui.checkbox(&mut cycle, "").changed() {
+                    time_of_day.cycle = cycle;
+                }
                 ui.end_row();
 
-                ui.label("Camera Pitch");
-                ui.label(format!("{:?}", pitch.to_degrees()));
+                ui.label("Coordinates");
+                let mut coordinates = *space;
+                ui.horizontal(|ui| {
+                    for option in [CoordinateSpace::World, CoordinateSpace::Origin] {
+                        ui.radio_value(&mut coordinates, option, option.name());
+                    }
+                });
+                if coordinates != *space {
+                    *space = coordinates;
+                }
                 ui.end_row();
 
-                ui.label("Camera Yaw");
-                ui.label(format!("{:?}", yaw.to_degrees()));
+                ui.label("Show Bounds");
+                let mut visible = markers.visible;
+                if ui.checkbox(&mut visible, "").changed() {
+                    markers.visible = visible;
+                }
                 ui.end_row();
+
+                section_settings(ui, &mut section);
             });
+        ui.label("Press F3 to toggle the debug overlay");
     });
+
+    // While the cursor is free the block under it is shown next to it as well
+    if let (Some(lines), false) = (&block_lines, fly_camera.enabled) {
+        let ctx = contexts.ctx_mut();
+        egui::show_tooltip_at_pointer(
+            ctx,
+            egui::LayerId::background(),
+            egui::Id::new("targeted_block_tooltip"),
+            |ui| {
+                ui.label(&lines[2]);
+                ui.label(&lines[1]);
+                ui.label(&lines[0]);
+            },
+        );
+    }
 }
 
 pub struct McDebugMenuPlugin;
 
 impl Plugin for McDebugMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EguiPlugin).add_systems(Update, show_gui);
+        app.add_plugins(EguiPlugin)
+            .insert_resource(DebugOverlay { visible: true })
+            .add_systems(Update, (toggle_overlay, show_gui));
     }
 }
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+/// Dye names in the order of their legacy numeric ids
+pub const DYES: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+pub fn rgb_color(rgb: u32) -> Color {
+    Color::srgb_u8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+/// The color used to tint textures dyed with this dye, like banner patterns
+pub fn diffuse_color(dye: &str) -> Option<Color> {
+    let rgb = match dye {
+        "white" => 0xF9FFFE,
+        "orange" => 0xF9801D,
+        "magenta" => 0xC74EBD,
+        "light_blue" => 0x3AB3DA,
+        "yellow" => 0xFED83D,
+        "lime" => 0x80C71F,
+        "pink" => 0xF38BAA,
+        "gray" => 0x474F52,
+        "light_gray" => 0x9D9D97,
+        "cyan" => 0x169C9C,
+        "purple" => 0x8932B8,
+        "blue" => 0x3C44AA,
+        "brown" => 0x835432,
+        "green" => 0x5E7C16,
+        "red" => 0xB02E26,
+        "black" => 0x1D1D21,
+        _ => return None,
+    };
+    Some(rgb_color(rgb))
+}
+
+/// The color of text dyed with this dye, like on signs
+pub fn text_color(dye: &str) -> Option<Color> {
+    let rgb = match dye {
+        "white" => 0xFFFFFF,
+        "orange" => 0xFF681F,
+        "magenta" => 0xFF00FF,
+        "light_blue" => 0x9AC0CD,
+        "yellow" => 0xFFFF00,
+        "lime" => 0xBFFF00,
+        "pink" => 0xFF69B4,
+        "gray" => 0x808080,
+        "light_gray" => 0xD3D3D3,
+        "cyan" => 0x00FFFF,
+        "purple" => 0xA020F0,
+        "blue" => 0x0000FF,
+        "brown" => 0x8B4513,
+        "green" => 0x00FF00,
+        "red" => 0xFF0000,
+        "black" => 0x000000,
+        _ => return None,
+    };
+    Some(rgb_color(rgb))
+}
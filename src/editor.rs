@@ -0,0 +1,488 @@
+//! Editing the schematic in the viewer. In edit mode left click breaks the targeted block, right
+//! click places the selected block state against the targeted face and middle click picks the
+//! targeted block. Edits can be undone and the result saved back to a `.schem` file.
+
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_fly_camera::FlyCamera;
+use color_eyre::Result;
+
+use crate::block::{init_blocks, split_block_state, Block, BlockBundle, BlockModels, BlockPalette};
+use crate::icon::BlockIcons;
+use crate::picking::{TargetedBlock, TargetedFace};
+use crate::resources::asset_pack::load_asset_pack;
+use crate::schematic::{write_schematic, BlockEntity, SchematicEntity};
+use crate::selection::Selection;
+use crate::{AppLoadState, BlockWorld, McCamera, SchematicRoot};
+
+/// Sets the block at a position of the schematic, replacing its block entity
+#[derive(Clone)]
+pub struct BlockChange {
+    pub pos: IVec3,
+    pub block: String,
+    pub block_entity: Option<BlockEntity>,
+}
+
+impl BlockChange {
+    pub fn new(pos: IVec3, block: &str) -> Self {
+        Self {
+            pos,
+            block: block.to_string(),
+            block_entity: None,
+        }
+    }
+}
+
+/// Changes the state of blocks that all have the same state. Their entities are kept and only
+/// get a new palette entry, which is much faster than changing them one by one.
+#[derive(Clone)]
+pub struct StateChange {
+    pub from: String,
+    pub to: String,
+    pub positions: Vec<IVec3>,
+    /// Block entities put back at the positions. The ones of blocks that turn into a different
+    /// block are removed.
+    pub block_entities: Vec<(IVec3, BlockEntity)>,
+}
+
+/// Changes blocks of the schematic as one step of the undo history
+#[derive(Event, Clone)]
+pub struct EditBlocks {
+    pub blocks: Vec<BlockChange>,
+    pub states: Vec<StateChange>,
+    /// All entities of the schematic, for edits that move entities along with the blocks
+    pub entities: Option<Vec<SchematicEntity>>,
+}
+
+impl EditBlocks {
+    pub fn new(blocks: Vec<BlockChange>) -> Self {
+        Self {
+            blocks,
+            states: Vec::new(),
+            entities: None,
+        }
+    }
+}
+
+/// Positions of the blocks an edit changed, sent once it has been applied
+#[derive(Event)]
+pub struct BlocksChanged(pub Vec<IVec3>);
+
+/// Sent once an edit replaced the entities of the schematic
+#[derive(Event)]
+pub struct EntitiesChanged;
+
+#[derive(Event)]
+enum HistoryStep {
+    Undo,
+    Redo,
+}
+
+#[derive(Resource)]
+pub struct Editor {
+    pub enabled: bool,
+    /// Block state placed by right clicking
+    selected: String,
+    /// Edits that revert each edit, the last edit being at the end
+    undo: Vec<EditBlocks>,
+    redo: Vec<EditBlocks>,
+    save_path: String,
+    status: Option<String>,
+}
+
+impl Editor {
+    /// Edits are saved next to the schematic rather than over it by default
+    pub fn new(schem_file: &Path) -> Self {
+        let stem = schem_file.file_stem().unwrap_or_default().to_string_lossy();
+        let save_path = schem_file.with_file_name(format!("{}_edited.schem", stem));
+        Self {
+            enabled: false,
+            selected: "minecraft:stone".to_string(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            save_path: save_path.display().to_string(),
+            status: None,
+        }
+    }
+
+    fn save(&mut self, block_world: &BlockWorld) {
+        let (sx, sy, sz) = block_world.blocks.size();
+        let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+        let result = write_schematic(block_world, IVec3::ZERO, size)
+            .and_then(|bytes| Ok(fs::write(&self.save_path, bytes)?));
+        self.status = Some(match result {
+            Ok(()) => format!("Saved to {}", self.save_path),
+            Err(err) => format!("Could not save: {}", err),
+        });
+    }
+}
+
+/// Sets a block and respawns its entity, returning the change that reverts it. Positions
+/// outside the schematic and changes that keep the block as it is are skipped.
+fn set_block(
+    commands: &mut Commands,
+    block_world: &mut BlockWorld,
+    palette: &mut BlockPalette,
+    root: Entity,
+    change: BlockChange,
+) -> Option<BlockChange> {
+    let (sx, sy, sz) = block_world.blocks.size();
+    let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+    let pos = change.pos;
+    if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(size).any() {
+        return None;
+    }
+    let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+    let before = block_world.blocks.get_block_at(x, y, z).to_string();
+    let has_block_entity = block_world.block_entities.contains_key(&pos);
+    if before == change.block && change.block_entity.is_none() && !has_block_entity {
+        return None;
+    }
+
+    let revert = BlockChange {
+        pos,
+        block: before,
+        block_entity: block_world.block_entities.remove(&pos),
+    };
+    block_world.blocks.set_block_at(x, y, z, &change.block);
+    if let Some(block_entity) = change.block_entity {
+        block_world.block_entities.insert(pos, block_entity);
+    }
+
+    // Each block is its own entity, so only the changed one has to be meshed again
+    let key = pos.as_uvec3();
+    if let Some(entity) = block_world.entities.remove(&key) {
+        commands.entity(entity).despawn_recursive();
+    }
+    if change.block != "minecraft:air" {
+        let idx = palette.get_or_add(&change.block);
+        let entity = commands
+            .spawn(BlockBundle::new(idx, pos))
+            .set_parent(root)
+            .id();
+        block_world.entities.insert(key, entity);
+    }
+    Some(revert)
+}
+
+/// Changes the state of blocks in place, returning the change that reverts it. Blocks that
+/// don't have the state it changes from are skipped.
+fn set_state(
+    commands: &mut Commands,
+    block_world: &mut BlockWorld,
+    palette: &mut BlockPalette,
+    blocks: &mut Query<&mut Block>,
+    root: Entity,
+    change: StateChange,
+) -> Option<StateChange> {
+    let (sx, sy, sz) = block_world.blocks.size();
+    let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+    let positions: Vec<_> = change
+        .positions
+        .into_iter()
+        .filter(|pos| pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size).all())
+        .filter(|pos| {
+            let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+            block_world.blocks.get_block_at(x, y, z) == change.from
+        })
+        .collect();
+    if positions.is_empty() || change.from == change.to {
+        return None;
+    }
+
+    // Block entity data only fits the block it came from
+    let mut removed = Vec::new();
+    if split_block_state(&change.from).0 != split_block_state(&change.to).0 {
+        for pos in &positions {
+            if let Some(block_entity) = block_world.block_entities.remove(pos) {
+                removed.push((*pos, block_entity));
+            }
+        }
+    }
+    block_world.block_entities.extend(change.block_entities);
+
+    let idx = palette.get_or_add(&change.to);
+    for &pos in &positions {
+        let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+        block_world.blocks.set_block_at(x, y, z, &change.to);
+        // Air has no entities, so only blocks turning into air or out of it are respawned
+        let key = pos.as_uvec3();
+        if change.to == "minecraft:air" {
+            if let Some(entity) = block_world.entities.remove(&key) {
+                commands.entity(entity).despawn_recursive();
+            }
+        } else if let Some(mut block) = block_world
+            .entities
+            .get(&key)
+            .and_then(|&entity| blocks.get_mut(entity).ok())
+        {
+            block.set_index(idx);
+        } else {
+            let entity = commands
+                .spawn(BlockBundle::new(idx, pos))
+                .set_parent(root)
+                .id();
+            block_world.entities.insert(key, entity);
+        }
+    }
+    Some(StateChange {
+        from: change.to,
+        to: change.from,
+        positions,
+        block_entities: removed,
+    })
+}
+
+/// Loads the models of the blocks an edit places that the schematic didn't have yet
+fn load_models(block_models: &mut BlockModels, edit: &EditBlocks) -> Result<()> {
+    let asset_pack = load_asset_pack()?;
+    let placed = edit.blocks.iter().map(|change| &change.block);
+    for block in placed.chain(edit.states.iter().map(|change| &change.to)) {
+        block_models.insert(&asset_pack, block)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_edits(
+    mut commands: Commands,
+    mut edits: EventReader<EditBlocks>,
+    mut steps: EventReader<HistoryStep>,
+    mut changed: EventWriter<BlocksChanged>,
+    mut entities_changed: EventWriter<EntitiesChanged>,
+    mut editor: ResMut<Editor>,
+    mut block_world: ResMut<BlockWorld>,
+    mut palette: ResMut<BlockPalette>,
+    mut block_models: ResMut<BlockModels>,
+    mut blocks: Query<&mut Block>,
+    root: Query<Entity, With<SchematicRoot>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let mut apply = |edit: EditBlocks, editor: &mut Editor| {
+        if let Err(err) = load_models(&mut block_models, &edit) {
+            warn!("Could not load block models for an edit: {}", err);
+            editor.status = Some(format!("Could not load block model: {}", err));
+            return None;
+        }
+        let changes: Vec<_> = edit
+            .blocks
+            .into_iter()
+            .filter_map(|change| {
+                set_block(&mut commands, &mut block_world, &mut palette, root, change)
+            })
+            .collect();
+        let states: Vec<_> = edit
+            .states
+            .into_iter()
+            .filter_map(|change| {
+                set_state(
+                    &mut commands,
+                    &mut block_world,
+                    &mut palette,
+                    &mut blocks,
+                    root,
+                    change,
+                )
+            })
+            .collect();
+        let entities = edit
+            .entities
+            .map(|entities| mem::replace(&mut block_world.schematic_entities, entities));
+        if changes.is_empty() && states.is_empty() && entities.is_none() {
+            return None;
+        }
+        if !changes.is_empty() || !states.is_empty() {
+            let positions = changes.iter().map(|change| change.pos);
+            let states_positions = states.iter().flat_map(|change| change.positions.clone());
+            changed.send(BlocksChanged(positions.chain(states_positions).collect()));
+        }
+        if entities.is_some() {
+            entities_changed.send(EntitiesChanged);
+        }
+        // Reverting goes backwards, so a position changed twice ends up as it was first
+        Some(EditBlocks {
+            blocks: changes.into_iter().rev().collect(),
+            states: states.into_iter().rev().collect(),
+            entities,
+        })
+    };
+
+    for edit in edits.read() {
+        if let Some(revert) = apply(edit.clone(), &mut *editor) {
+            editor.undo.push(revert);
+            editor.redo.clear();
+        }
+    }
+    for step in steps.read() {
+        let edit = match step {
+            HistoryStep::Undo => editor.undo.pop(),
+            HistoryStep::Redo => editor.redo.pop(),
+        };
+        let Some(revert) = edit.and_then(|edit| apply(edit, &mut *editor)) else {
+            continue;
+        };
+        match step {
+            HistoryStep::Undo => editor.redo.push(revert),
+            HistoryStep::Redo => editor.undo.push(revert),
+        }
+    }
+}
+
+/// E toggles edit mode, which takes over the mouse from select mode. Ctrl+Z undoes, Ctrl+Y or
+/// Ctrl+Shift+Z redoes and Ctrl+S saves.
+fn edit_keys(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut steps: EventWriter<HistoryStep>,
+    mut selection: ResMut<Selection>,
+    block_world: Res<BlockWorld>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !ctrl {
+        if keyboard_input.just_pressed(KeyCode::KeyE) {
+            editor.enabled = !editor.enabled;
+            selection.enabled &= !editor.enabled;
+        }
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyZ) {
+        steps.send(if shift {
+            HistoryStep::Redo
+        } else {
+            HistoryStep::Undo
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyY) {
+        steps.send(HistoryStep::Redo);
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyS) {
+        editor.save(&block_world);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn edit_with_mouse(
+    mut contexts: EguiContexts,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    targeted: Res<TargetedBlock>,
+    targeted_face: Res<TargetedFace>,
+    block_world: Res<BlockWorld>,
+    fly_camera: Query<&FlyCamera, With<McCamera>>,
+    mut editor: ResMut<Editor>,
+    mut edits: EventWriter<EditBlocks>,
+) {
+    if !editor.enabled {
+        return;
+    }
+    let grabbed = fly_camera.get_single().is_ok_and(|camera| camera.enabled);
+    if !grabbed && contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    let Some(pos) = targeted.0 else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        edits.send(EditBlocks::new(vec![BlockChange::new(
+            pos,
+            "minecraft:air",
+        )]));
+    }
+    if mouse_input.just_pressed(MouseButton::Right) {
+        let Some(face) = targeted_face.0 else {
+            return;
+        };
+        // The schematic can't grow, so blocks can only be placed into air inside it
+        let place = pos + face;
+        let (sx, sy, sz) = block_world.blocks.size();
+        let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+        if place.cmplt(IVec3::ZERO).any() || place.cmpge(size).any() {
+            editor.status = Some("Blocks can't be placed outside the schematic".to_string());
+        } else {
+            let (x, y, z) = (place.x as usize, place.y as usize, place.z as usize);
+            if block_world.blocks.get_block_at(x, y, z) == "minecraft:air" {
+                let change = BlockChange::new(place, &editor.selected);
+                edits.send(EditBlocks::new(vec![change]));
+            }
+        }
+    }
+    if mouse_input.just_pressed(MouseButton::Middle) {
+        let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+        editor.selected = block_world.blocks.get_block_at(x, y, z).to_string();
+    }
+}
+
+fn editor_window(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    mut steps: EventWriter<HistoryStep>,
+    mut selection: ResMut<Selection>,
+    block_world: Res<BlockWorld>,
+    icons: Res<BlockIcons>,
+) {
+    let editor = &mut *editor;
+    egui::Window::new("Edit")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if ui.checkbox(&mut editor.enabled, "Edit mode (E)").changed() {
+                selection.enabled &= !editor.enabled;
+            }
+            ui.label("Left click breaks, right click places and middle click picks a block");
+            ui.horizontal(|ui| {
+                if let Some(icon) = icons.block_icon(&editor.selected) {
+                    ui.add(icon);
+                }
+                ui.text_edit_singleline(&mut editor.selected);
+            });
+            ui.horizontal(|ui| {
+                let undo = egui::Button::new(format!("Undo ({})", editor.undo.len()));
+                if ui.add_enabled(!editor.undo.is_empty(), undo).clicked() {
+                    steps.send(HistoryStep::Undo);
+                }
+                let redo = egui::Button::new(format!("Redo ({})", editor.redo.len()));
+                if ui.add_enabled(!editor.redo.is_empty(), redo).clicked() {
+                    steps.send(HistoryStep::Redo);
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut editor.save_path);
+                if ui.button("Save").clicked() {
+                    editor.save(&block_world);
+                }
+            });
+            if let Some(status) = &editor.status {
+                ui.label(status);
+            }
+        });
+}
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EditBlocks>()
+            .add_event::<BlocksChanged>()
+            .add_event::<EntitiesChanged>()
+            .add_event::<HistoryStep>()
+            .add_systems(
+                Update,
+                (edit_keys, edit_with_mouse, editor_window, apply_edits)
+                    .chain()
+                    .before(init_blocks)
+                    .run_if(in_state(AppLoadState::Finished)),
+            );
+    }
+}
@@ -0,0 +1,722 @@
+//! Entities stored in the schematic. Only the kinds that are part of builds get rendered: item
+//! frames, armor stands, paintings, minecarts, boats and dropped items. Their geometry follows
+//! the vanilla models and renderer transforms, like block entities do.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use nbt::Value;
+
+use crate::block::{BlockBundle, BlockMaterial, BlockPalette};
+use crate::editor::EntitiesChanged;
+use crate::item::{ItemDisplay, ItemModels};
+use crate::model::{part_rotation, Cube, EntityModel, Part, QuadMesh};
+use crate::resources::textures::{TextureAtlas, TextureDetails};
+use crate::schematic::SchematicEntity;
+use crate::{AppLoadState, BlockWorld, SchematicRoot};
+
+fn entity_name(entity: &SchematicEntity) -> &str {
+    entity.id.trim_start_matches("minecraft:")
+}
+
+fn flag(entity: &SchematicEntity, key: &str) -> bool {
+    matches!(entity.get(key), Some(Value::Byte(1..)))
+}
+
+fn byte(entity: &SchematicEntity, keys: &[&str]) -> Option<i8> {
+    keys.iter().find_map(|key| match entity.get(key) {
+        Some(Value::Byte(value)) => Some(*value),
+        _ => None,
+    })
+}
+
+fn string<'a>(entity: &'a SchematicEntity, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|key| match entity.get(key) {
+        Some(Value::String(value)) => Some(value.as_str()),
+        _ => None,
+    })
+}
+
+/// Step and vanilla rotation of the 3D direction data values used by item frames
+fn direction(data: i8) -> (Vec3, f32, f32) {
+    match data {
+        0 => (Vec3::NEG_Y, 90.0, 0.0),
+        1 => (Vec3::Y, -90.0, 0.0),
+        2 => (Vec3::NEG_Z, 0.0, 180.0),
+        4 => (Vec3::NEG_X, 0.0, 90.0),
+        5 => (Vec3::X, 0.0, 270.0),
+        _ => (Vec3::Z, 0.0, 0.0),
+    }
+}
+
+fn y_rotation(degrees: f32) -> Mat4 {
+    Mat4::from_rotation_y(degrees.to_radians())
+}
+
+/// Turns the upside down space of vanilla entity models right side up
+fn flip_model() -> Mat4 {
+    Mat4::from_scale(Vec3::new(-1.0, -1.0, 1.0))
+}
+
+/// The id of an item stack, empty slots are left out or hold air
+fn item_id(stack: &Value) -> Option<&str> {
+    let Value::Compound(stack) = stack else {
+        return None;
+    };
+    match stack.get("id") {
+        Some(Value::String(id)) if id != "minecraft:air" => Some(id.as_str()),
+        _ => None,
+    }
+}
+
+fn item_frame_model(entity: &SchematicEntity) -> Option<String> {
+    let name = entity_name(entity);
+    if !matches!(name, "item_frame" | "glow_item_frame") || flag(entity, "Invisible") {
+        return None;
+    }
+    let has_map = match entity.get("Item") {
+        Some(Value::Compound(item)) => {
+            matches!(item.get("id"), Some(Value::String(id)) if id == "minecraft:filled_map")
+        }
+        _ => false,
+    };
+    let suffix = if has_map { "_map" } else { "" };
+    Some(format!("minecraft:block/{}{}", name, suffix))
+}
+
+/// The block shown inside a minecart and how far up it sits, in pixels
+fn minecart_display_block(entity: &SchematicEntity) -> Option<(String, i32)> {
+    let (default_block, default_offset) = match entity_name(entity) {
+        "chest_minecart" => (
+            "minecraft:chest[facing=north,type=single,waterlogged=false]",
+            8,
+        ),
+        "hopper_minecart" => ("minecraft:hopper[enabled=true,facing=down]", 1),
+        "furnace_minecart" => ("minecraft:furnace[facing=north,lit=false]", 6),
+        "tnt_minecart" => ("minecraft:tnt[unstable=false]", 6),
+        "spawner_minecart" => ("minecraft:spawner", 6),
+        "command_block_minecart" => ("minecraft:command_block[conditional=false,facing=north]", 6),
+        "minecart" => ("", 6),
+        _ => return None,
+    };
+    let offset = match entity.get("DisplayOffset") {
+        Some(Value::Int(offset)) if flag(entity, "CustomDisplayTile") => *offset,
+        _ => default_offset,
+    };
+
+    let block = match entity.get("DisplayState") {
+        Some(Value::Compound(state)) if flag(entity, "CustomDisplayTile") => {
+            let Some(Value::String(name)) = state.get("Name") else {
+                return None;
+            };
+            let mut props: Vec<_> = match state.get("Properties") {
+                Some(Value::Compound(props)) => props
+                    .iter()
+                    .filter_map(|(key, value)| match value {
+                        Value::String(value) => Some(format!("{}={}", key, value)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            // Block states are keyed by their string, so the order needs to be stable
+            props.sort();
+            if props.is_empty() {
+                name.clone()
+            } else {
+                format!("{}[{}]", name, props.join(","))
+            }
+        }
+        _ => default_block.to_string(),
+    };
+    if block.is_empty() || block == "minecraft:air" {
+        return None;
+    }
+    Some((block, offset))
+}
+
+/// Block states and block models that entities render through the block pipeline, which
+/// have to be loaded along with the models of the schematic's blocks
+pub fn entity_block_models(entities: &[SchematicEntity]) -> Vec<String> {
+    entities
+        .iter()
+        .filter_map(|entity| {
+            item_frame_model(entity).or_else(|| minecart_display_block(entity).map(|(b, _)| b))
+        })
+        .collect()
+}
+
+/// Rotation of an item frame towards the wall it hangs on, in pixels relative to the entity
+fn item_frame_rotation(entity: &SchematicEntity) -> Mat4 {
+    let (step, x_rot, y_rot) = direction(byte(entity, &["Facing"]).unwrap_or(3));
+    Mat4::from_translation(step * 0.46875 * 16.0)
+        * Mat4::from_rotation_x(x_rot.to_radians())
+        * y_rotation(180.0 - y_rot)
+}
+
+/// Transform of an item frame's block model, in pixels relative to the entity
+fn item_frame_transform(entity: &SchematicEntity) -> Mat4 {
+    // The entity sits against the wall, the model is centered on the block
+    item_frame_rotation(entity) * Mat4::from_translation(Vec3::splat(-8.0))
+}
+
+/// The item in an item frame, turned in steps of 45 degrees. Maps aren't drawn since the map
+/// contents aren't part of the schematic.
+fn item_frame_item(entity: &SchematicEntity) -> Option<(&str, Mat4)> {
+    let item = item_id(entity.get("Item")?)?;
+    if item == "minecraft:filled_map" {
+        return None;
+    }
+    let rotation = byte(entity, &["ItemRotation"]).unwrap_or(0).rem_euclid(8);
+    // Without the frame the item moves back against the wall
+    let depth = if flag(entity, "Invisible") { 8.0 } else { 7.0 };
+    let transform = item_frame_rotation(entity)
+        * Mat4::from_translation(Vec3::new(0.0, 0.0, depth))
+        * Mat4::from_rotation_z((rotation as f32 * 45.0).to_radians())
+        * Mat4::from_scale(Vec3::splat(0.5));
+    Some((item, transform))
+}
+
+/// Rotation of the minecart body, in pixels relative to the entity
+fn minecart_transform(entity: &SchematicEntity) -> Mat4 {
+    let (yaw, pitch) = entity.rotation();
+    Mat4::from_translation(Vec3::new(0.0, 6.0, 0.0))
+        * y_rotation(180.0 - yaw)
+        * Mat4::from_rotation_z((-pitch).to_radians())
+}
+
+fn minecart_model(entity: &SchematicEntity) -> EntityModel {
+    let side = |transform| Part {
+        transform,
+        cubes: vec![Cube::new(0.0, 0.0, [-8.0, -9.0, -1.0], [16.0, 8.0, 2.0])],
+    };
+    let offset = |x, z| Mat4::from_translation(Vec3::new(x, 4.0, z));
+    let parts = vec![
+        Part {
+            transform: offset(0.0, 0.0) * Mat4::from_rotation_x(PI / 2.0),
+            cubes: vec![Cube::new(0.0, 10.0, [-10.0, -8.0, -1.0], [20.0, 16.0, 2.0])],
+        },
+        side(offset(-9.0, 0.0) * Mat4::from_rotation_y(PI * 1.5)),
+        side(offset(9.0, 0.0) * Mat4::from_rotation_y(PI / 2.0)),
+        side(offset(0.0, -7.0) * Mat4::from_rotation_y(PI)),
+        side(offset(0.0, 7.0)),
+        Part {
+            transform: offset(0.0, 0.0) * Mat4::from_rotation_x(-PI / 2.0),
+            cubes: vec![Cube::new(44.0, 10.0, [-9.0, -7.0, -1.0], [18.0, 14.0, 1.0])],
+        },
+    ];
+    EntityModel {
+        texture: "entity/minecart".to_string(),
+        parts,
+    }
+    .transformed(minecart_transform(entity) * flip_model())
+}
+
+/// Transform of the block inside a minecart, in pixels relative to the entity
+fn minecart_display_transform(entity: &SchematicEntity, offset: i32) -> Mat4 {
+    minecart_transform(entity)
+        * Mat4::from_scale(Vec3::splat(0.75))
+        * Mat4::from_translation(Vec3::new(-8.0, offset as f32 - 8.0, 8.0))
+        * y_rotation(90.0)
+}
+
+fn boat_model(entity: &SchematicEntity) -> Option<EntityModel> {
+    let name = entity_name(entity);
+    // Up to 1.21.1 there's one id per kind of boat, with the wood stored separately
+    let (wood, chest) = match name {
+        "boat" | "chest_boat" => (
+            string(entity, &["Type"]).unwrap_or("oak"),
+            name == "chest_boat",
+        ),
+        _ => match name.strip_suffix("_chest_boat") {
+            Some(wood) => (wood, true),
+            None => (name.strip_suffix("_boat")?, false),
+        },
+    };
+    if wood == "bamboo" {
+        warn!("Bamboo rafts aren't supported yet");
+        return None;
+    }
+
+    let offset = |x, y, z| Mat4::from_translation(Vec3::new(x, y, z));
+    let side = |u, v, transform, x, width| Part {
+        transform,
+        cubes: vec![Cube::new(u, v, [x, -7.0, -1.0], [width, 6.0, 2.0])],
+    };
+    // The paddles at rest, which is the start of the rowing animation
+    let paddle_x = -PI / 3.0 + (PI / 3.0 - PI / 12.0) * 0.5;
+    let paddle_y = -PI / 4.0 + PI / 2.0 * (1.0f32.sin() + 1.0) / 2.0;
+    let paddle = |v, transform, blade_x| Part {
+        transform,
+        cubes: vec![
+            Cube::new(62.0, v, [-1.0, 0.0, -5.0], [2.0, 2.0, 18.0]),
+            Cube::new(62.0, v, [blade_x, -3.0, 8.0], [1.0, 6.0, 7.0]),
+        ],
+    };
+    let mut parts = vec![
+        Part {
+            transform: offset(0.0, 3.0, 1.0) * Mat4::from_rotation_x(PI / 2.0),
+            cubes: vec![Cube::new(0.0, 0.0, [-14.0, -9.0, -3.0], [28.0, 16.0, 3.0])],
+        },
+        side(
+            0.0,
+            19.0,
+            offset(-15.0, 4.0, 4.0) * Mat4::from_rotation_y(PI * 1.5),
+            -13.0,
+            18.0,
+        ),
+        side(
+            0.0,
+            27.0,
+            offset(15.0, 4.0, 0.0) * Mat4::from_rotation_y(PI / 2.0),
+            -8.0,
+            16.0,
+        ),
+        side(
+            0.0,
+            35.0,
+            offset(0.0, 4.0, -9.0) * Mat4::from_rotation_y(PI),
+            -14.0,
+            28.0,
+        ),
+        side(0.0, 43.0, offset(0.0, 4.0, 9.0), -14.0, 28.0),
+        paddle(
+            0.0,
+            offset(3.0, -5.0, 9.0) * part_rotation(paddle_x, paddle_y, PI / 16.0),
+            -1.001,
+        ),
+        paddle(
+            20.0,
+            offset(3.0, -5.0, -9.0) * part_rotation(paddle_x, PI - paddle_y, PI / 16.0),
+            0.001,
+        ),
+    ];
+    if chest {
+        let chest_part = |u, v, transform, size| Part {
+            transform: transform * Mat4::from_rotation_y(-PI / 2.0),
+            cubes: vec![Cube::new(u, v, [0.0, 0.0, 0.0], size)],
+        };
+        parts.extend([
+            chest_part(0.0, 76.0, offset(-2.0, -5.0, -6.0), [12.0, 8.0, 12.0]),
+            chest_part(0.0, 59.0, offset(-2.0, -9.0, -6.0), [12.0, 4.0, 12.0]),
+            chest_part(0.0, 59.0, offset(-1.0, -6.0, -1.0), [2.0, 4.0, 1.0]),
+        ]);
+    }
+
+    let folder = if chest { "chest_boat" } else { "boat" };
+    let (yaw, _) = entity.rotation();
+    let model = EntityModel {
+        texture: format!("entity/{}/{}", folder, wood),
+        parts,
+    };
+    Some(model.transformed(
+        Mat4::from_translation(Vec3::new(0.0, 6.0, 0.0))
+            * y_rotation(180.0 - yaw)
+            * flip_model()
+            * y_rotation(90.0),
+    ))
+}
+
+fn armor_stand_model(entity: &SchematicEntity) -> Option<EntityModel> {
+    if flag(entity, "Invisible") {
+        return None;
+    }
+
+    let pose = |key, default| armor_stand_pose(entity, key, default);
+    let offset = |x, y| Mat4::from_translation(Vec3::new(x, y, 0.0));
+    let body = pose("Body", [0.0, 0.0, 0.0]);
+
+    let mut parts = vec![
+        Part {
+            transform: armor_stand_head(entity),
+            cubes: vec![Cube::new(0.0, 0.0, [-1.0, -7.0, -1.0], [2.0, 7.0, 2.0])],
+        },
+        Part {
+            transform: body,
+            cubes: vec![
+                Cube::new(0.0, 26.0, [-6.0, 0.0, -1.5], [12.0, 3.0, 3.0]),
+                Cube::new(16.0, 0.0, [-3.0, 3.0, -1.0], [2.0, 7.0, 2.0]),
+                Cube::new(48.0, 16.0, [1.0, 3.0, -1.0], [2.0, 7.0, 2.0]),
+                Cube::new(0.0, 48.0, [-4.0, 10.0, -1.0], [8.0, 2.0, 2.0]),
+            ],
+        },
+        Part {
+            transform: offset(-1.9, 12.0) * pose("RightLeg", [1.0, 0.0, 1.0]),
+            cubes: vec![Cube::new(8.0, 0.0, [-1.0, 0.0, -1.0], [2.0, 11.0, 2.0])],
+        },
+        Part {
+            transform: offset(1.9, 12.0) * pose("LeftLeg", [-1.0, 0.0, -1.0]),
+            cubes: vec![Cube::new(40.0, 16.0, [-1.0, 0.0, -1.0], [2.0, 11.0, 2.0]).mirror()],
+        },
+    ];
+    if flag(entity, "ShowArms") {
+        parts.push(Part {
+            transform: armor_stand_arm(entity, false),
+            cubes: vec![Cube::new(24.0, 0.0, [-2.0, -2.0, -1.0], [2.0, 12.0, 2.0])],
+        });
+        parts.push(Part {
+            transform: armor_stand_arm(entity, true),
+            cubes: vec![Cube::new(32.0, 16.0, [0.0, -2.0, -1.0], [2.0, 12.0, 2.0]).mirror()],
+        });
+    }
+    if !flag(entity, "NoBasePlate") {
+        parts.push(Part {
+            transform: offset(0.0, 12.0),
+            cubes: vec![Cube::new(0.0, 32.0, [-6.0, 11.0, -6.0], [12.0, 1.0, 12.0])],
+        });
+    }
+
+    let model = EntityModel {
+        texture: "entity/armorstand/wood".to_string(),
+        parts,
+    };
+    Some(model.transformed(armor_stand_transform(entity)))
+}
+
+/// Transform from the armor stand's model space to pixels relative to the entity
+fn armor_stand_transform(entity: &SchematicEntity) -> Mat4 {
+    let (yaw, _) = entity.rotation();
+    let scale = if flag(entity, "Small") { 0.5 } else { 1.0 };
+    y_rotation(180.0 - yaw)
+        * Mat4::from_scale(Vec3::splat(scale))
+        * flip_model()
+        * Mat4::from_translation(Vec3::new(0.0, -24.016, 0.0))
+}
+
+/// Rotation of a part of an armor stand from its `Pose`, in degrees like the NBT
+fn armor_stand_pose(entity: &SchematicEntity, key: &str, default: [f32; 3]) -> Mat4 {
+    let rotation = match entity.get("Pose") {
+        Some(Value::Compound(pose)) => match pose.get(key) {
+            Some(Value::List(angles)) => {
+                let angle = |idx: usize| match angles.get(idx) {
+                    Some(Value::Float(angle)) => *angle,
+                    _ => default[idx],
+                };
+                [angle(0), angle(1), angle(2)]
+            }
+            _ => default,
+        },
+        _ => default,
+    };
+    let [x, y, z] = rotation.map(f32::to_radians);
+    part_rotation(x, y, z)
+}
+
+fn armor_stand_head(entity: &SchematicEntity) -> Mat4 {
+    Mat4::from_translation(Vec3::new(0.0, 1.0, 0.0))
+        * armor_stand_pose(entity, "Head", [0.0, 0.0, 0.0])
+}
+
+fn armor_stand_arm(entity: &SchematicEntity, left: bool) -> Mat4 {
+    if left {
+        Mat4::from_translation(Vec3::new(5.0, 2.0, 0.0))
+            * armor_stand_pose(entity, "LeftArm", [-10.0, 0.0, -10.0])
+    } else {
+        Mat4::from_translation(Vec3::new(-5.0, 2.0, 0.0))
+            * armor_stand_pose(entity, "RightArm", [-15.0, 0.0, 10.0])
+    }
+}
+
+/// The items an armor stand holds and wears on its head, as main hand, off hand and head. The
+/// equipment moved from lists into a compound in 1.21.5.
+fn armor_stand_equipment(entity: &SchematicEntity) -> [Option<&str>; 3] {
+    if let Some(Value::Compound(equipment)) = entity.get("equipment") {
+        return ["mainhand", "offhand", "head"].map(|slot| equipment.get(slot).and_then(item_id));
+    }
+    let slot = |key, idx: usize| match entity.get(key) {
+        Some(Value::List(items)) => items.get(idx).and_then(item_id),
+        _ => None,
+    };
+    [
+        slot("HandItems", 0),
+        slot("HandItems", 1),
+        slot("ArmorItems", 3),
+    ]
+}
+
+/// Items held by an armor stand, following vanilla's `ItemInHandLayer` and `CustomHeadLayer`.
+/// Helmets are armor rather than items on the head, which isn't drawn yet.
+fn armor_stand_items(entity: &SchematicEntity) -> Vec<(&str, ItemDisplay, Mat4)> {
+    let [main_hand, off_hand, head] = armor_stand_equipment(entity);
+    let root = armor_stand_transform(entity);
+    let mut items = Vec::new();
+    for (item, left) in [(main_hand, false), (off_hand, true)] {
+        let Some(item) = item else {
+            continue;
+        };
+        let side = if left { -1.0 } else { 1.0 };
+        let display = if left {
+            ItemDisplay::ThirdPersonLeftHand
+        } else {
+            ItemDisplay::ThirdPersonRightHand
+        };
+        let transform = root
+            * armor_stand_arm(entity, left)
+            * Mat4::from_rotation_x(-PI / 2.0)
+            * Mat4::from_rotation_y(PI)
+            * Mat4::from_translation(Vec3::new(side, 2.0, -10.0));
+        items.push((item, display, transform));
+    }
+    if let Some(item) = head.filter(|item| !item.ends_with("_helmet")) {
+        let transform = root
+            * armor_stand_head(entity)
+            * Mat4::from_translation(Vec3::new(0.0, -4.0, 0.0))
+            * Mat4::from_rotation_y(PI)
+            * Mat4::from_scale(Vec3::new(0.625, -0.625, -0.625));
+        items.push((item, ItemDisplay::Head, transform));
+    }
+    items
+}
+
+/// Items an entity shows, with the transform of the point they're held at in pixels relative
+/// to the entity
+fn displayed_items(entity: &SchematicEntity) -> Vec<(&str, ItemDisplay, Mat4)> {
+    match entity_name(entity) {
+        "item_frame" | "glow_item_frame" => item_frame_item(entity)
+            .map(|(item, transform)| (item, ItemDisplay::Fixed, transform))
+            .into_iter()
+            .collect(),
+        "armor_stand" => armor_stand_items(entity),
+        // Dropped items bob up and down, this is the middle of the animation
+        "item" => entity
+            .get("Item")
+            .and_then(item_id)
+            .map(|item| {
+                let transform = Mat4::from_translation(Vec3::new(0.0, 1.6, 0.0));
+                (item, ItemDisplay::Ground, transform)
+            })
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Items shown by entities, whose models have to be loaded along with the block models
+pub fn entity_items(entities: &[SchematicEntity]) -> Vec<String> {
+    entities
+        .iter()
+        .flat_map(displayed_items)
+        .map(|(item, _, _)| item.to_string())
+        .collect()
+}
+
+/// Size in blocks of the vanilla painting variants
+fn painting_size(variant: &str) -> Option<(u32, u32)> {
+    let size = match variant {
+        "kebab" | "aztec" | "alban" | "aztec2" | "bomb" | "plant" | "wasteland" | "meditative" => {
+            (1, 1)
+        }
+        "pool" | "courbet" | "sea" | "sunset" | "creebet" => (2, 1),
+        "wanderer" | "graham" | "prairie_ride" => (1, 2),
+        "match" | "bust" | "stage" | "void" | "skull_and_roses" | "wither" | "earth" | "wind"
+        | "water" | "fire" | "baroque" | "humble" => (2, 2),
+        "bouquet" | "cavebird" | "cotan" | "endboss" | "fern" | "owlemons" | "sunflowers"
+        | "tides" => (3, 3),
+        "backyard" | "pond" => (3, 4),
+        "fighters" | "changing" | "finding" | "lowmist" | "passage" => (4, 2),
+        "skeleton" | "donkey_kong" => (4, 3),
+        "pointer" | "pigscene" | "burning_skull" | "orb" | "unpacked" => (4, 4),
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// A painting centered on the entity, with its front facing north before rotation
+fn painting_mesh(entity: &SchematicEntity, atlas: &TextureAtlas) -> Option<(Mesh, Mat4)> {
+    // The variant was called the motive before 1.19
+    let variant = string(entity, &["variant", "Motive"])?;
+    let (namespace, variant) = variant.split_once(':').unwrap_or(("minecraft", variant));
+    let texture = format!("{}:painting/{}", namespace, variant);
+    if !atlas.has_texture(&texture) {
+        warn!("Unknown painting variant {}", variant);
+        return None;
+    }
+    let front = atlas.get_tex_details(&texture);
+    let back = atlas.get_tex_details("painting/back");
+    let (width, height) = painting_size(variant).unwrap_or((
+        (front.size.x / 16.0).max(1.0) as u32,
+        (front.size.y / 16.0).max(1.0) as u32,
+    ));
+
+    let rect = |details: &TextureDetails, u1, v1, u2, v2| {
+        let [u1, v1] = details.get_atlas_uvs(u1, v1);
+        let [u2, v2] = details.get_atlas_uvs(u2, v2);
+        [u1, v1, u2, v2]
+    };
+    let mut mesh = QuadMesh::default();
+    let half = Vec2::new(width as f32, height as f32) * 8.0;
+    mesh.quad(
+        Vec3::new(0.0, 0.0, -0.5),
+        Vec3::NEG_Z,
+        half,
+        rect(&front, 0.0, 0.0, 1.0, 1.0),
+        Color::WHITE,
+    );
+    // The back and edges are tiled per block
+    let edge = 1.0 / 16.0;
+    for tx in 0..width {
+        for ty in 0..height {
+            let center = Vec2::new(tx as f32 * 16.0 + 8.0, ty as f32 * 16.0 + 8.0) - half;
+            mesh.quad(
+                center.extend(0.5),
+                Vec3::Z,
+                Vec2::splat(8.0),
+                rect(&back, 0.0, 0.0, 1.0, 1.0),
+                Color::WHITE,
+            );
+            let edges = [
+                (Vec3::NEG_Y, ty == 0),
+                (Vec3::Y, ty == height - 1),
+                (Vec3::NEG_X, tx == 0),
+                (Vec3::X, tx == width - 1),
+            ];
+            for (normal, on_edge) in edges {
+                if !on_edge {
+                    continue;
+                }
+                let (center, size, uv) = if normal.y != 0.0 {
+                    (
+                        Vec3::new(center.x, normal.y * half.y, 0.0),
+                        Vec2::new(8.0, 0.5),
+                        rect(&back, 0.0, 0.0, 1.0, edge),
+                    )
+                } else {
+                    (
+                        Vec3::new(normal.x * half.x, center.y, 0.0),
+                        Vec2::new(0.5, 8.0),
+                        rect(&back, 0.0, 0.0, edge, 1.0),
+                    )
+                };
+                mesh.quad(center, normal, size, uv, Color::WHITE);
+            }
+        }
+    }
+
+    // Paintings store a horizontal direction as south, west, north, east
+    let facing = byte(entity, &["facing", "Facing"]).unwrap_or(0);
+    let transform = y_rotation(180.0 - facing as f32 * 90.0);
+    Some((mesh.into_mesh(), transform))
+}
+
+/// Marks what's spawned for each entity of the schematic, so it can be spawned again
+#[derive(Component)]
+struct EntityView;
+
+/// Spawns the entities of the schematic once it's loaded, and again whenever an edit moved them
+#[allow(clippy::too_many_arguments)]
+fn spawn_schematic_entities(
+    mut commands: Commands,
+    mut spawned: Local<bool>,
+    mut material: Local<Option<Handle<BlockMaterial>>>,
+    mut changed: EventReader<EntitiesChanged>,
+    views: Query<Entity, With<EntityView>>,
+    block_world: Res<BlockWorld>,
+    atlas: Res<TextureAtlas>,
+    item_models: Res<ItemModels>,
+    mut palette: ResMut<BlockPalette>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
+    root: Query<Entity, With<SchematicRoot>>,
+) {
+    if *spawned && changed.is_empty() {
+        return;
+    }
+    changed.clear();
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    *spawned = true;
+    for view in views.iter() {
+        commands.entity(view).despawn_recursive();
+    }
+    // Entity models share the block material so they're clipped along with the blocks
+    let material = material.get_or_insert_with(|| {
+        materials.add(BlockMaterial {
+            base: StandardMaterial {
+                base_color_texture: Some(atlas.image.clone()),
+                perceptual_roughness: 1.0,
+                reflectance: 0.0,
+                alpha_mode: AlphaMode::Mask(0.5),
+                ..default()
+            },
+            extension: default(),
+        })
+    });
+    for entity in &block_world.schematic_entities {
+        let name = entity_name(entity);
+        // Models in pixels relative to the entity, and blocks rendered by the block pipeline
+        let mut models = Vec::new();
+        let mut blocks = Vec::new();
+        let model_mesh = |model: EntityModel| (model.atlas_mesh(&atlas), Mat4::IDENTITY);
+        if let Some(model) = item_frame_model(entity) {
+            blocks.push((model, item_frame_transform(entity)));
+        } else if name.ends_with("minecart") {
+            models.push(model_mesh(minecart_model(entity)));
+            if let Some((block, offset)) = minecart_display_block(entity) {
+                blocks.push((block, minecart_display_transform(entity, offset)));
+            }
+        } else if name.ends_with("boat") {
+            models.extend(boat_model(entity).map(model_mesh));
+        } else if name == "armor_stand" {
+            models.extend(armor_stand_model(entity).map(model_mesh));
+        } else if name == "painting" {
+            models.extend(painting_mesh(entity, &atlas));
+        }
+        for (item, display, transform) in displayed_items(entity) {
+            let Some(item_model) = item_models.get(item) else {
+                continue;
+            };
+            // Dropped items float higher the larger they're drawn
+            let lift = if display == ItemDisplay::Ground {
+                item_model.display(display).scale[1] * 4.0
+            } else {
+                0.0
+            };
+            let transform = Mat4::from_translation(Vec3::new(0.0, lift, 0.0))
+                * transform
+                * item_model.transform(display);
+            blocks.push((item_model.model.clone(), transform));
+        }
+        if models.is_empty() && blocks.is_empty() {
+            continue;
+        }
+
+        let parent = commands
+            .spawn((
+                SpatialBundle {
+                    transform: Transform::from_translation(entity.pos)
+                        .with_scale(Vec3::splat(1.0 / 16.0)),
+                    ..default()
+                },
+                EntityView,
+            ))
+            .set_parent(root)
+            .id();
+        for (mesh, transform) in models {
+            commands
+                .spawn(MaterialMeshBundle {
+                    mesh: meshes.add(mesh),
+                    material: material.clone(),
+                    transform: Transform::from_matrix(transform),
+                    ..default()
+                })
+                .set_parent(parent);
+        }
+        for (block, transform) in blocks {
+            let mut bundle = BlockBundle::new(palette.get_or_add(&block), IVec3::ZERO);
+            bundle.pbr.transform = Transform::from_matrix(transform);
+            commands.spawn(bundle).set_parent(parent);
+        }
+    }
+}
+
+pub struct SchematicEntityPlugin;
+
+impl Plugin for SchematicEntityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            spawn_schematic_entities.run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::core_pipeline::Skybox;
+use bevy::pbr::{FogFalloff, FogSettings};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureViewDescriptor, TextureViewDimension,
+};
+use bevy::render::texture::ImageSampler;
+use clap::ValueEnum;
+
+use crate::time_of_day::Sun;
+use crate::McCamera;
+
+/// Ambient brightness at full light level
+const MAX_AMBIENT_BRIGHTNESS: f32 = 1000.0;
+/// Illuminance of the fixed light used in dimensions without a day/night cycle
+const FIXED_LIGHT_ILLUMINANCE: f32 = 1000.0;
+
+#[derive(Resource, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dimension {
+    #[default]
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    /// Whether the dimension has a sun, moon and atmosphere driven by the time of day
+    pub fn has_sky_cycle(self) -> bool {
+        self == Dimension::Overworld
+    }
+
+    pub fn fog_color(self) -> Color {
+        match self {
+            Dimension::Overworld => Color::srgb(0.75, 0.85, 1.0),
+            Dimension::Nether => Color::srgb_u8(0x33, 0x08, 0x08),
+            Dimension::End => Color::srgb_u8(0x18, 0x13, 0x18),
+        }
+    }
+
+    pub fn fog_settings(self) -> FogSettings {
+        let (start, end) = match self {
+            Dimension::Overworld => (256.0, 1024.0),
+            Dimension::Nether => (32.0, 192.0),
+            Dimension::End => (128.0, 512.0),
+        };
+        FogSettings {
+            color: self.fog_color(),
+            falloff: FogFalloff::Linear { start, end },
+            ..default()
+        }
+    }
+
+    /// The `ambient_light` value of the vanilla dimension type
+    fn ambient_light(self) -> f32 {
+        match self {
+            Dimension::Nether => 0.1,
+            Dimension::Overworld | Dimension::End => 0.0,
+        }
+    }
+
+    /// Minecraft's lightmap curve, mapping a light level from 0 to 15 to a brightness from 0 to 1
+    pub fn light_brightness(self, level: u8) -> f32 {
+        let f = level.min(15) as f32 / 15.0;
+        let curved = f / (4.0 - 3.0 * f);
+        self.ambient_light().lerp(1.0, curved)
+    }
+
+    /// Light level used for ambient light in dimensions without a sky
+    fn fixed_light_level(self) -> u8 {
+        match self {
+            Dimension::Overworld => 15,
+            Dimension::Nether => 11,
+            Dimension::End => 12,
+        }
+    }
+}
+
+/// Run condition for systems that drive the sun, moon and atmosphere
+pub fn dimension_has_sky_cycle(dimension: Res<Dimension>) -> bool {
+    dimension.has_sky_cycle()
+}
+
+/// The End sky texture and the cubemap created from it once it's loaded
+#[derive(Resource)]
+struct EndSky {
+    texture: Handle<Image>,
+    cubemap: Option<Handle<Image>>,
+}
+
+fn setup_environment(
+    mut commands: Commands,
+    dimension: Res<Dimension>,
+    mut ambient_light: ResMut<AmbientLight>,
+    asset_server: Res<AssetServer>,
+) {
+    if dimension.has_sky_cycle() {
+        // The time of day takes care of the sky and lights
+        return;
+    }
+
+    commands.insert_resource(ClearColor(dimension.fog_color()));
+    ambient_light.brightness =
+        MAX_AMBIENT_BRIGHTNESS * dimension.light_brightness(dimension.fixed_light_level());
+    commands.spawn((
+        Sun,
+        DirectionalLightBundle {
+            transform: Transform::from_xyz(1.0, 3.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+            directional_light: DirectionalLight {
+                illuminance: FIXED_LIGHT_ILLUMINANCE,
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+
+    if *dimension == Dimension::End {
+        commands.insert_resource(EndSky {
+            texture: asset_server.load("minecraft/textures/environment/end_sky.png"),
+            cubemap: None,
+        });
+    }
+}
+
+/// Creates a cubemap with the End sky texture on every face and attaches it to the camera
+fn create_end_skybox(
+    mut commands: Commands,
+    mut end_sky: ResMut<EndSky>,
+    mut images: ResMut<Assets<Image>>,
+    camera: Query<Entity, With<McCamera>>,
+) {
+    if end_sky.cubemap.is_some() {
+        return;
+    }
+    let Some(texture) = images.get(&end_sky.texture) else {
+        return;
+    };
+
+    let mut data = Vec::with_capacity(texture.data.len() * 6);
+    for _ in 0..6 {
+        data.extend_from_slice(&texture.data);
+    }
+    let mut cubemap = Image::new(
+        Extent3d {
+            width: texture.width(),
+            height: texture.height() * 6,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        texture.texture_descriptor.format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    cubemap.reinterpret_stacked_2d_as_array(6);
+    cubemap.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    cubemap.sampler = ImageSampler::nearest();
+
+    let cubemap = images.add(cubemap);
+    for entity in camera.iter() {
+        commands.entity(entity).insert(Skybox {
+            image: cubemap.clone(),
+            // Vanilla tints the End sky to a very dark grey
+            brightness: 160.0,
+        });
+    }
+    end_sky.cubemap = Some(cubemap);
+}
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Dimension>()
+            .add_systems(Startup, setup_environment)
+            .add_systems(Update, create_end_skybox.run_if(resource_exists::<EndSky>));
+    }
+}
@@ -0,0 +1,258 @@
+//! Inventory style icons for blocks and items, drawn on the CPU from the same geometry as the
+//! blocks in the world using the `gui` transform of the item model. The icons are packed into
+//! one atlas that the interface shows them from.
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::block::{block_geometry, BlockModels};
+use crate::item::{ItemDisplay, ItemModel, ItemModels};
+use crate::material_list::block_items;
+use crate::resources::textures::TextureAtlas;
+use crate::AppLoadState;
+
+/// Size icons are drawn at, in pixels, which is twice the size of an inventory slot
+const ICON_SIZE: usize = 32;
+const ATLAS_COLUMNS: usize = 16;
+/// Size icons are shown at in lists, in points
+const ICON_POINTS: f32 = 16.0;
+/// Directions of the two lights vanilla lights blocks in the inventory with, in view space
+const GUI_LIGHTS: [Vec3; 2] = [Vec3::new(0.2, 1.0, -0.7), Vec3::new(-0.2, 1.0, 0.7)];
+
+/// Icons of all loaded item models, by item id
+#[derive(Resource, Default)]
+pub struct BlockIcons {
+    texture: Option<egui::TextureId>,
+    uvs: HashMap<String, egui::Rect>,
+}
+
+impl BlockIcons {
+    pub fn item_icon(&self, item: &str) -> Option<egui::Image<'static>> {
+        let uv = self.uvs.get(item)?;
+        let texture = (self.texture?, egui::Vec2::splat(ICON_POINTS));
+        Some(egui::Image::new(texture).uv(*uv))
+    }
+
+    /// Icon of the item a block state is placed from
+    pub fn block_icon(&self, block: &str) -> Option<egui::Image<'static>> {
+        let (item, _) = block_items(block).into_iter().next()?;
+        self.item_icon(&item)
+    }
+}
+
+/// The texture atlas as it's kept on the CPU
+struct AtlasPixels<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl AtlasPixels<'_> {
+    /// Nearest texel at normalized atlas coordinates, in sRGB
+    fn sample(&self, uv: Vec2) -> Vec4 {
+        let x = ((uv.x * self.width as f32) as usize).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as usize).min(self.height - 1);
+        let idx = (y * self.width + x) * 4;
+        Vec4::from_array(std::array::from_fn(|c| self.data[idx + c] as f32 / 255.0))
+    }
+}
+
+/// One icon being drawn, with colors in sRGB and straight alpha
+struct Canvas {
+    color: Vec<Vec4>,
+    depth: Vec<f32>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            color: vec![Vec4::ZERO; ICON_SIZE * ICON_SIZE],
+            depth: vec![f32::NEG_INFINITY; ICON_SIZE * ICON_SIZE],
+        }
+    }
+
+    /// Rasterizes a triangle given in pixels of the icon, with larger depth being closer. Fully
+    /// opaque texels are drawn in the first pass and translucent ones are blended over them in
+    /// the second.
+    fn triangle(
+        &mut self,
+        atlas: &AtlasPixels,
+        corners: [(Vec3, Vec2); 3],
+        shade: Vec4,
+        translucent_pass: bool,
+    ) {
+        let [(a, uv_a), (b, uv_b), (c, uv_c)] = corners;
+        let area = (b - a).truncate().perp_dot((c - a).truncate());
+        if area.abs() < 1.0e-6 {
+            return;
+        }
+        let min = a.min(b).min(c).truncate().floor().max(Vec2::ZERO);
+        let max = a
+            .max(b)
+            .max(c)
+            .truncate()
+            .ceil()
+            .min(Vec2::splat(ICON_SIZE as f32));
+
+        for y in min.y as usize..max.y as usize {
+            for x in min.x as usize..max.x as usize {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let wa = (b - p.extend(0.0)).truncate().perp_dot((c - b).truncate()) / area;
+                let wb = (c - p.extend(0.0)).truncate().perp_dot((a - c).truncate()) / area;
+                let wc = 1.0 - wa - wb;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let idx = y * ICON_SIZE + x;
+                let depth = a.z * wa + b.z * wb + c.z * wc;
+                if depth <= self.depth[idx] {
+                    continue;
+                }
+                let texel = atlas.sample(uv_a * wa + uv_b * wb + uv_c * wc) * shade;
+                let opaque = texel.w >= 1.0;
+                if texel.w <= 0.0 || opaque == translucent_pass {
+                    continue;
+                }
+                if opaque {
+                    self.color[idx] = texel;
+                    self.depth[idx] = depth;
+                } else {
+                    let below = self.color[idx];
+                    let alpha = texel.w + below.w * (1.0 - texel.w);
+                    let rgb = (texel.truncate() * texel.w
+                        + below.truncate() * below.w * (1.0 - texel.w))
+                        / alpha;
+                    self.color[idx] = rgb.extend(alpha);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the icon of an item from the geometry of its model
+fn draw_icon(
+    atlas_pixels: &AtlasPixels,
+    atlas: &TextureAtlas,
+    block_models: &BlockModels,
+    item_model: &ItemModel,
+) -> Canvas {
+    let (geometry, tint) = block_geometry(&item_model.model, atlas, block_models);
+    let transform = item_model.transform(ItemDisplay::Gui);
+    let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+    let tint = tint.map_or(Vec4::ONE, |tint| {
+        Vec4::from_array(tint.to_srgba().to_f32_array())
+    });
+    let scale = ICON_SIZE as f32 / 16.0;
+
+    // Positions in pixels of the icon, with y pointing down
+    let corners: Vec<_> = geometry
+        .positions
+        .iter()
+        .zip(&geometry.uvs)
+        .map(|(&pos, &uv)| {
+            let pos = transform.transform_point3(Vec3::from_array(pos));
+            let pos = Vec3::new((pos.x + 8.0) * scale, (8.0 - pos.y) * scale, pos.z);
+            (pos, Vec2::from_array(uv))
+        })
+        .collect();
+
+    let mut canvas = Canvas::new();
+    for translucent_pass in [false, true] {
+        for triangle in geometry.indices.chunks_exact(3) {
+            // Faces are one sided, so the ones facing away aren't drawn
+            let normal = (normal_matrix * Vec3::from_array(geometry.normals[triangle[0] as usize]))
+                .normalize_or_zero();
+            if normal.z <= 0.0 {
+                continue;
+            }
+            let light = if item_model.front_lit {
+                1.0
+            } else {
+                let diffuse: f32 = GUI_LIGHTS
+                    .iter()
+                    .map(|light| normal.dot(light.normalize()).max(0.0))
+                    .sum();
+                (0.4 + 0.6 * diffuse).min(1.0)
+            };
+            let shade = (tint.truncate() * light).extend(tint.w);
+            let corners = [0, 1, 2].map(|idx| corners[triangle[idx] as usize]);
+            canvas.triangle(atlas_pixels, corners, shade, translucent_pass);
+        }
+    }
+    canvas
+}
+
+fn build_icons(
+    mut contexts: EguiContexts,
+    mut icons: ResMut<BlockIcons>,
+    mut images: ResMut<Assets<Image>>,
+    atlas: Res<TextureAtlas>,
+    block_models: Res<BlockModels>,
+    item_models: Res<ItemModels>,
+) {
+    let mut items: Vec<_> = item_models.iter().collect();
+    items.sort_by_key(|(item, _)| *item);
+    let rows = items.len().div_ceil(ATLAS_COLUMNS).max(1);
+    let (width, height) = (ATLAS_COLUMNS * ICON_SIZE, rows * ICON_SIZE);
+    let mut data = vec![0; width * height * 4];
+
+    let Some(atlas_image) = images.get(&atlas.image) else {
+        return;
+    };
+    let atlas_pixels = AtlasPixels {
+        data: &atlas_image.data,
+        width: atlas_image.width() as usize,
+        height: atlas_image.height() as usize,
+    };
+    for (idx, (item, item_model)) in items.into_iter().enumerate() {
+        let canvas = draw_icon(&atlas_pixels, &atlas, &block_models, item_model);
+        let (column, row) = (idx % ATLAS_COLUMNS, idx / ATLAS_COLUMNS);
+        for (pixel, color) in canvas.color.iter().enumerate() {
+            let x = column * ICON_SIZE + pixel % ICON_SIZE;
+            let y = row * ICON_SIZE + pixel / ICON_SIZE;
+            let offset = (y * width + x) * 4;
+            for (c, value) in color.to_array().into_iter().enumerate() {
+                data[offset + c] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+
+        let min = egui::pos2(
+            (column * ICON_SIZE) as f32 / width as f32,
+            (row * ICON_SIZE) as f32 / height as f32,
+        );
+        let size = egui::vec2(
+            ICON_SIZE as f32 / width as f32,
+            ICON_SIZE as f32 / height as f32,
+        );
+        icons
+            .uvs
+            .insert(item.to_string(), egui::Rect::from_min_size(min, size));
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    icons.texture = Some(contexts.add_image(images.add(image)));
+}
+
+pub struct IconPlugin;
+
+impl Plugin for IconPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockIcons>()
+            .add_systems(OnEnter(AppLoadState::Finished), build_icons);
+    }
+}
@@ -0,0 +1,257 @@
+//! Panel showing the block entity data of a clicked block. Containers get their inventory laid
+//! out as slots, command blocks their command and signs their text, and the full NBT is shown
+//! as a tree below that.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_fly_camera::FlyCamera;
+use nbt::Value;
+
+use crate::editor::Editor;
+use crate::lang::Lang;
+use crate::origin::CoordinateSpace;
+use crate::picking::TargetedBlock;
+use crate::schematic::BlockEntity;
+use crate::selection::Selection;
+use crate::sign::sign_lines;
+use crate::{AppLoadState, BlockWorld, McCamera};
+
+/// Position of the block whose block entity is shown
+#[derive(Resource, Default)]
+pub struct InspectedBlock(pub Option<IVec3>);
+
+/// Columns and number of slots of a container's inventory
+fn container_layout(id: &str) -> Option<(usize, usize)> {
+    let layout = match id.trim_start_matches("minecraft:") {
+        "chest" | "trapped_chest" | "barrel" | "shulker_box" => (9, 27),
+        "hopper" | "brewing_stand" => (5, 5),
+        "dispenser" | "dropper" | "crafter" => (3, 9),
+        "furnace" | "smoker" | "blast_furnace" => (3, 3),
+        "campfire" | "soul_campfire" => (4, 4),
+        "chiseled_bookshelf" => (3, 6),
+        _ => return None,
+    };
+    Some(layout)
+}
+
+/// An item in a container, which stores its count as `Count` before 1.20.5
+struct Slot<'a> {
+    id: &'a str,
+    count: i32,
+    stack: &'a Value,
+}
+
+impl<'a> Slot<'a> {
+    fn read(stack: &'a Value) -> Option<(usize, Self)> {
+        let Value::Compound(compound) = stack else {
+            return None;
+        };
+        let Some(Value::String(id)) = compound.get("id") else {
+            return None;
+        };
+        let slot = match compound.get("Slot") {
+            Some(Value::Byte(slot)) => usize::try_from(*slot).ok()?,
+            _ => return None,
+        };
+        let count = match (compound.get("count"), compound.get("Count")) {
+            (Some(Value::Int(count)), _) => *count,
+            (_, Some(Value::Byte(count))) => *count as i32,
+            _ => 1,
+        };
+        Some((slot, Self { id, count, stack }))
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::Byte(value) => format!("{}b", value),
+        Value::Short(value) => format!("{}s", value),
+        Value::Int(value) => value.to_string(),
+        Value::Long(value) => format!("{}L", value),
+        Value::Float(value) => format!("{}f", value),
+        Value::Double(value) => format!("{}d", value),
+        Value::String(value) => format!("{:?}", value),
+        Value::ByteArray(values) => format!("{:?}", values),
+        Value::IntArray(values) => format!("{:?}", values),
+        Value::LongArray(values) => format!("{:?}", values),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// Shows NBT as collapsible compounds and lists, with scalars inline
+fn nbt_tree(ui: &mut egui::Ui, name: &str, value: &Value) {
+    match value {
+        Value::Compound(compound) => {
+            let mut entries: Vec<_> = compound.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            egui::CollapsingHeader::new(format!("{} {{{}}}", name, entries.len())).show(ui, |ui| {
+                for (key, value) in entries {
+                    nbt_tree(ui, key, value);
+                }
+            });
+        }
+        Value::List(list) => {
+            egui::CollapsingHeader::new(format!("{} [{}]", name, list.len())).show(ui, |ui| {
+                for (idx, value) in list.iter().enumerate() {
+                    nbt_tree(ui, &idx.to_string(), value);
+                }
+            });
+        }
+        _ => {
+            let text = scalar_text(value).unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", name));
+                ui.monospace(text);
+            });
+        }
+    }
+}
+
+fn slot_grid(ui: &mut egui::Ui, lang: &Lang, items: &[Value], columns: usize, slots: usize) {
+    let mut contents: Vec<Option<Slot>> = (0..slots).map(|_| None).collect();
+    for (idx, slot) in items.iter().filter_map(Slot::read) {
+        if idx < slots {
+            contents[idx] = Some(slot);
+        }
+    }
+
+    egui::Grid::new("slot_grid")
+        .spacing([4.0, 4.0])
+        .show(ui, |ui| {
+            for (idx, slot) in contents.iter().enumerate() {
+                let text = match slot {
+                    Some(slot) => format!("{}\n×{}", lang.item_name(slot.id), slot.count),
+                    None => String::new(),
+                };
+                let response = egui::Frame::group(ui.style())
+                    .show(ui, |ui| {
+                        ui.add_sized(
+                            [72.0, 32.0],
+                            egui::Label::new(egui::RichText::new(text).small()).wrap(),
+                        );
+                    })
+                    .response;
+                if let Some(slot) = slot {
+                    response.on_hover_ui(|ui| {
+                        ui.label(format!("Slot {}", idx));
+                        nbt_tree(ui, slot.id, slot.stack);
+                    });
+                }
+                if (idx + 1) % columns == 0 {
+                    ui.end_row();
+                }
+            }
+        });
+}
+
+/// The parts of a block entity that are worth showing above the raw NBT
+fn summary(ui: &mut egui::Ui, lang: &Lang, block_entity: &BlockEntity) {
+    let id = block_entity.id.as_str();
+    if let Some(Value::List(items)) = block_entity.get("Items") {
+        // Modded containers get a chest sized grid that fits all of their slots
+        let (columns, slots) = container_layout(id).unwrap_or_else(|| {
+            let used = items.iter().filter_map(Slot::read).map(|(idx, _)| idx + 1);
+            (9, used.max().unwrap_or(0).max(27))
+        });
+        slot_grid(ui, lang, items, columns, slots);
+    }
+    if let Some(Value::String(command)) = block_entity.get("Command") {
+        ui.label("Command");
+        ui.add(
+            egui::TextEdit::multiline(&mut command.as_str())
+                .code_editor()
+                .desired_rows(1),
+        );
+        if let Some(Value::String(output)) = block_entity.get("LastOutput") {
+            ui.label(format!("Last output: {}", output));
+        }
+    }
+    if id.ends_with("sign") {
+        for (front, side) in [(true, "Front"), (false, "Back")] {
+            if let Some(lines) = sign_lines(block_entity, front) {
+                ui.label(side);
+                ui.monospace(lines.join("\n"));
+            }
+        }
+    }
+    if let Some(Value::Int(signal)) = block_entity.get("OutputSignal") {
+        ui.label(format!("Output signal: {}", signal));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn select_block(
+    mut contexts: EguiContexts,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    targeted: Res<TargetedBlock>,
+    block_world: Res<BlockWorld>,
+    fly_camera: Query<&FlyCamera, With<McCamera>>,
+    editor: Res<Editor>,
+    selection: Res<Selection>,
+    mut inspected: ResMut<InspectedBlock>,
+) {
+    // Clicks break blocks in edit mode and set corners in select mode
+    let other_tool = editor.enabled || selection.enabled;
+    if !mouse_input.just_pressed(MouseButton::Left) || other_tool {
+        return;
+    }
+    let grabbed = fly_camera.get_single().is_ok_and(|camera| camera.enabled);
+    if !grabbed && contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    inspected.0 = targeted
+        .0
+        .filter(|pos| block_world.block_entities.contains_key(pos));
+}
+
+fn show_inspector(
+    mut contexts: EguiContexts,
+    block_world: Res<BlockWorld>,
+    space: Res<CoordinateSpace>,
+    lang: Res<Lang>,
+    mut inspected: ResMut<InspectedBlock>,
+) {
+    let Some(pos) = inspected.0 else {
+        return;
+    };
+    let Some(block_entity) = block_world.block_entities.get(&pos) else {
+        return;
+    };
+    let block = block_world
+        .blocks
+        .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+
+    let mut open = true;
+    egui::Window::new("Block Entity")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let shown = pos + space.offset(&block_world);
+            ui.label(format!(
+                "{} at {} {} {}",
+                lang.block_name(block),
+                shown.x,
+                shown.y,
+                shown.z
+            ));
+            ui.monospace(block);
+            ui.separator();
+            summary(ui, &lang, block_entity);
+            ui.separator();
+            nbt_tree(ui, &block_entity.id, &block_entity.data);
+        });
+    if !open {
+        inspected.0 = None;
+    }
+}
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InspectedBlock>().add_systems(
+            Update,
+            (select_block, show_inspector).run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
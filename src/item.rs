@@ -0,0 +1,308 @@
+//! Item models, for items that are shown in the world by item frames, armor stands and dropped
+//! items. Items are rendered through the block pipeline: models with elements are used as they
+//! are, flat `item/generated` sprites get extruded into elements like vanilla does, and items
+//! drawn by a block entity renderer use the block of the same name.
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use minecraft_assets::api::AssetPack;
+use minecraft_assets::schemas::models::{Element, Textures};
+use serde::Deserialize;
+
+use crate::block::BlockModels;
+use crate::resources::asset_pack::resource_path;
+use crate::resources::textures::{resolve_textures_completely, CpuImage};
+
+/// The parts of a model file that matter for items. The elements and textures use the same
+/// schema as block models.
+#[derive(Deserialize)]
+struct ModelFile {
+    parent: Option<String>,
+    #[serde(default)]
+    display: HashMap<String, ItemTransform>,
+    textures: Option<Textures>,
+    elements: Option<Vec<Element>>,
+    /// How the model is lit in the inventory, `side` like a block or `front` like a sprite
+    gui_light: Option<String>,
+}
+
+fn load_model_file(location: &str) -> Result<ModelFile> {
+    let path = resource_path("models", &format!("{}.json", location));
+    let json = fs::read_to_string(&path)
+        .map_err(|err| eyre!("Could not read model {}: {}", path.display(), err))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn with_namespace(location: &str) -> String {
+    if location.contains(':') {
+        location.to_string()
+    } else {
+        format!("minecraft:{}", location)
+    }
+}
+
+fn one() -> [f32; 3] {
+    [1.0; 3]
+}
+
+/// A transform from the `display` section of a model, with the translation in pixels and the
+/// rotation in degrees
+#[derive(Deserialize, Clone, Copy)]
+pub struct ItemTransform {
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default)]
+    pub translation: [f32; 3],
+    #[serde(default = "one")]
+    pub scale: [f32; 3],
+}
+
+impl Default for ItemTransform {
+    fn default() -> Self {
+        Self {
+            rotation: [0.0; 3],
+            translation: [0.0; 3],
+            scale: one(),
+        }
+    }
+}
+
+impl ItemTransform {
+    /// Vanilla mirrors the transform of the right hand for the left hand
+    fn matrix(&self, left_hand: bool) -> Mat4 {
+        let [x, mut y, mut z] = self.rotation.map(f32::to_radians);
+        let mut translation =
+            Vec3::from_array(self.translation).clamp(Vec3::splat(-80.0), Vec3::splat(80.0));
+        if left_hand {
+            y = -y;
+            z = -z;
+            translation.x = -translation.x;
+        }
+        let scale = Vec3::from_array(self.scale).clamp(Vec3::splat(-4.0), Vec3::splat(4.0));
+        Mat4::from_translation(translation)
+            * Mat4::from_quat(Quat::from_euler(EulerRot::XYZ, x, y, z))
+            * Mat4::from_scale(scale)
+    }
+}
+
+/// The ways an item can be shown, which pick the transform from the model's `display` section
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ItemDisplay {
+    ThirdPersonRightHand,
+    ThirdPersonLeftHand,
+    Head,
+    Ground,
+    Fixed,
+    Gui,
+}
+
+impl ItemDisplay {
+    fn key(self) -> &'static str {
+        match self {
+            ItemDisplay::ThirdPersonRightHand => "thirdperson_righthand",
+            ItemDisplay::ThirdPersonLeftHand => "thirdperson_lefthand",
+            ItemDisplay::Head => "head",
+            ItemDisplay::Ground => "ground",
+            ItemDisplay::Fixed => "fixed",
+            ItemDisplay::Gui => "gui",
+        }
+    }
+}
+
+pub struct ItemModel {
+    /// Name of the model in [`BlockModels`], which can be spawned with a `BlockBundle`
+    pub model: String,
+    display: HashMap<String, ItemTransform>,
+    /// Whether the inventory lights the model from the front, which flat items are
+    pub front_lit: bool,
+}
+
+impl ItemModel {
+    pub fn display(&self, display: ItemDisplay) -> ItemTransform {
+        let transform = self.display.get(display.key());
+        let transform = match display {
+            // Models usually only define the right hand
+            ItemDisplay::ThirdPersonLeftHand => {
+                transform.or_else(|| self.display.get("thirdperson_righthand"))
+            }
+            _ => transform,
+        };
+        transform.copied().unwrap_or_default()
+    }
+
+    /// Transform of the item's model, in pixels relative to the point the item is held at
+    pub fn transform(&self, display: ItemDisplay) -> Mat4 {
+        let left_hand = display == ItemDisplay::ThirdPersonLeftHand;
+        self.display(display).matrix(left_hand) * Mat4::from_translation(Vec3::splat(-8.0))
+    }
+}
+
+/// Extrudes the `layer0`, `layer1`, ... textures of a generated item into elements the way
+/// vanilla's `ItemModelGenerator` does: a front and back face covering the whole sprite, and
+/// a pixel sized side face wherever an opaque pixel borders a transparent one.
+fn generated_elements(textures: &Textures) -> Result<Vec<Element>> {
+    let layers: HashMap<&str, &str> = textures
+        .iter()
+        .map(|(name, texture)| (name.as_str(), texture.0.as_str()))
+        .collect();
+
+    let mut elements = Vec::new();
+    for layer in 0.. {
+        let name = format!("layer{}", layer);
+        let Some(location) = layers.get(name.as_str()) else {
+            break;
+        };
+        let texture = format!("#{}", name);
+        elements.push(serde_json::json!({
+            "from": [0.0, 0.0, 7.5],
+            "to": [16.0, 16.0, 8.5],
+            "faces": {
+                "south": { "uv": [0.0, 0.0, 16.0, 16.0], "texture": texture },
+                "north": { "uv": [16.0, 0.0, 0.0, 16.0], "texture": texture }
+            }
+        }));
+
+        let image = CpuImage::load(&format!("{}.png", location))?;
+        // Animated textures have their frames stacked vertically, only the first one is used
+        let width = image.width;
+        let height = image.height.min(width);
+        let opaque = |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && x < width as i64
+                && y < height as i64
+                && image.is_opaque(x as u32, y as u32)
+        };
+        let (px_width, px_height) = (16.0 / width as f32, 16.0 / height as f32);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                if !opaque(x, y) {
+                    continue;
+                }
+                let x0 = x as f32 * px_width;
+                let x1 = x0 + px_width;
+                let y1 = 16.0 - y as f32 * px_height;
+                let y0 = y1 - px_height;
+                let uv = [x0, 16.0 - y1, x1, 16.0 - y0];
+                let sides = [
+                    (opaque(x, y - 1), "up", [x0, y1], [x1, y1]),
+                    (opaque(x, y + 1), "down", [x0, y0], [x1, y0]),
+                    (opaque(x - 1, y), "west", [x0, y0], [x0, y1]),
+                    (opaque(x + 1, y), "east", [x1, y0], [x1, y1]),
+                ];
+                for (covered, face, [from_x, from_y], [to_x, to_y]) in sides {
+                    if covered {
+                        continue;
+                    }
+                    elements.push(serde_json::json!({
+                        "from": [from_x, from_y, 7.5],
+                        "to": [to_x, to_y, 8.5],
+                        "faces": { face: { "uv": uv, "texture": texture } }
+                    }));
+                }
+            }
+        }
+    }
+
+    elements
+        .into_iter()
+        .map(|element| Ok(serde_json::from_value(element)?))
+        .collect()
+}
+
+/// The models of the items shown in the world, by item id
+#[derive(Resource, Default)]
+pub struct ItemModels(HashMap<String, ItemModel>);
+
+impl ItemModels {
+    pub fn get(&self, item: &str) -> Option<&ItemModel> {
+        self.0.get(&with_namespace(item))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ItemModel)> {
+        self.0.iter().map(|(item, model)| (item.as_str(), model))
+    }
+
+    /// Loads the model of an item like `minecraft:stick`, adding its geometry to the block
+    /// models
+    pub fn insert(
+        &mut self,
+        asset_pack: &AssetPack,
+        block_models: &mut BlockModels,
+        item: &str,
+    ) -> Result<()> {
+        let item = with_namespace(item);
+        if self.0.contains_key(&item) {
+            return Ok(());
+        }
+        let (namespace, path) = item.split_once(':').unwrap();
+        let model_name = format!("{}:item/{}", namespace, path);
+
+        // Walk up the parents, with the child's display transforms and elements taking
+        // precedence over the parent's
+        let mut location = model_name.clone();
+        let mut display = HashMap::new();
+        let mut elements = None;
+        let mut gui_light = None;
+        let mut texture_chain = Vec::new();
+        let builtin = loop {
+            let model = load_model_file(&location)?;
+            for (context, transform) in model.display {
+                display.entry(context).or_insert(transform);
+            }
+            if elements.is_none() {
+                elements = model.elements;
+            }
+            gui_light = gui_light.or(model.gui_light);
+            texture_chain.extend(model.textures);
+
+            let Some(parent) = model.parent else {
+                break None;
+            };
+            let parent = with_namespace(&parent);
+            match parent.as_str() {
+                "minecraft:builtin/generated" | "minecraft:builtin/entity" => break Some(parent),
+                _ => location = parent,
+            }
+        };
+
+        let mut textures = Textures::default();
+        for mut model_textures in texture_chain.into_iter().rev() {
+            model_textures.merge(textures);
+            textures = model_textures;
+        }
+
+        let model = match builtin.as_deref() {
+            // Chests, beds, skulls and the like are drawn like the block
+            Some("minecraft:builtin/entity") => {
+                block_models.insert(asset_pack, &item)?;
+                item.clone()
+            }
+            Some(_) => {
+                let textures = resolve_textures_completely(textures);
+                let elements = generated_elements(&textures)?;
+                block_models.insert_elements(&model_name, textures, elements);
+                model_name
+            }
+            None => {
+                let textures = resolve_textures_completely(textures);
+                block_models.insert_elements(&model_name, textures, elements.unwrap_or_default());
+                model_name
+            }
+        };
+        let front_lit = gui_light.as_deref() == Some("front");
+        self.0.insert(
+            item,
+            ItemModel {
+                model,
+                display,
+                front_lit,
+            },
+        );
+        Ok(())
+    }
+}
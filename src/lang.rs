@@ -0,0 +1,85 @@
+//! Display names of blocks and items from the asset pack's language files, so lists can show
+//! `Light Weighted Pressure Plate` rather than `minecraft:light_weighted_pressure_plate`.
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use color_eyre::Result;
+
+use crate::block::split_block_state;
+use crate::resources::asset_pack::resource_path;
+use crate::StartupWarnings;
+
+/// Locale the game ships inside its jar, which every other locale falls back to
+const DEFAULT_LOCALE: &str = "en_us";
+
+/// Turns an id like `minecraft:redstone_torch` into `Redstone Torch`, for ids without a
+/// translation
+fn fallback_name(id: &str) -> String {
+    let (_, path) = id.split_once(':').unwrap_or(("minecraft", id));
+    path.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn read_lang_file(locale: &str) -> Result<HashMap<String, String>> {
+    let path = resource_path("lang", &format!("minecraft:{}.json", locale));
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Translations of the selected locale, on top of the default locale
+#[derive(Resource, Default)]
+pub struct Lang {
+    translations: HashMap<String, String>,
+}
+
+impl Lang {
+    pub fn load(locale: &str, warnings: &mut StartupWarnings) -> Self {
+        let mut locales = vec![DEFAULT_LOCALE];
+        if locale != DEFAULT_LOCALE {
+            locales.push(locale);
+        }
+        let mut translations = HashMap::new();
+        for locale in locales {
+            match read_lang_file(locale) {
+                Ok(file) => translations.extend(file),
+                Err(err) => warnings.push(format!("Could not load language {}: {}", locale, err)),
+            }
+        }
+        Self { translations }
+    }
+
+    fn translate(&self, kind: &str, id: &str) -> Option<&str> {
+        let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+        let key = format!("{}.{}.{}", kind, namespace, path.replace('/', "."));
+        self.translations.get(&key).map(String::as_str)
+    }
+
+    /// Name of a block, given its id or a whole block state
+    pub fn block_name(&self, block: &str) -> String {
+        let (id, _) = split_block_state(block);
+        match self.translate("block", id) {
+            Some(name) => name.to_string(),
+            None => fallback_name(id),
+        }
+    }
+
+    /// Name of an item, where items that place a block may only have the block's translation
+    pub fn item_name(&self, id: &str) -> String {
+        match self
+            .translate("item", id)
+            .or_else(|| self.translate("block", id))
+        {
+            Some(name) => name.to_string(),
+            None => fallback_name(id),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Estimates of Minecraft's light levels, for the debug overlay. Light is spread from emitting
+//! blocks and the sky the way vanilla does, but nothing outside the schematic is known, so the
+//! space around it counts as open sky.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::block::{parse_props, split_block_state, BlockModels};
+use crate::BlockWorld;
+
+const MAX_LIGHT: u8 = 15;
+
+/// Full blocks that light still passes through
+const TRANSPARENT_BLOCKS: &[&str] = &[
+    "ice",
+    "frosted_ice",
+    "slime_block",
+    "honey_block",
+    "spawner",
+    "beacon",
+    "barrier",
+];
+
+/// Light emitted by a block state, following the values vanilla gives each block
+pub fn luminance(block: &str) -> u8 {
+    let (name, props) = split_block_state(block);
+    let props = parse_props(props);
+    let prop = |key: &str| props.get(key).copied().unwrap_or_default();
+    let number = |key: &str| prop(key).parse::<u8>().unwrap_or_default();
+    let lit = prop("lit") == "true";
+
+    let name = name.trim_start_matches("minecraft:");
+    let name = name.trim_start_matches("waxed_");
+    match name {
+        "glowstone"
+        | "sea_lantern"
+        | "lantern"
+        | "jack_o_lantern"
+        | "beacon"
+        | "shroomlight"
+        | "fire"
+        | "lava"
+        | "lava_cauldron"
+        | "end_portal"
+        | "end_gateway"
+        | "conduit"
+        | "ochre_froglight"
+        | "verdant_froglight"
+        | "pearlescent_froglight" => 15,
+        "torch" | "wall_torch" | "end_rod" => 14,
+        "cave_vines" | "cave_vines_plant" if prop("berries") == "true" => 14,
+        "nether_portal" => 11,
+        "soul_torch" | "soul_wall_torch" | "soul_lantern" | "soul_fire" | "crying_obsidian" => 10,
+        "enchanting_table" | "ender_chest" | "glow_lichen" => 7,
+        "sculk_catalyst" => 6,
+        "amethyst_cluster" => 5,
+        "large_amethyst_bud" => 4,
+        "magma_block" => 3,
+        "medium_amethyst_bud" | "firefly_bush" => 2,
+        "small_amethyst_bud"
+        | "brewing_stand"
+        | "brown_mushroom"
+        | "dragon_egg"
+        | "end_portal_frame"
+        | "sculk_sensor"
+        | "calibrated_sculk_sensor" => 1,
+        "light" => number("level"),
+        "respawn_anchor" => number("charges") * MAX_LIGHT / 4,
+        "sea_pickle" if prop("waterlogged") == "true" => 3 + 3 * number("pickles"),
+        _ if !lit => 0,
+        "redstone_lamp" | "campfire" => 15,
+        "furnace" | "smoker" | "blast_furnace" => 13,
+        "soul_campfire" => 10,
+        "redstone_ore" | "deepslate_redstone_ore" => 9,
+        "redstone_torch" | "redstone_wall_torch" => 7,
+        "copper_bulb" => 15,
+        "exposed_copper_bulb" => 12,
+        "weathered_copper_bulb" => 8,
+        "oxidized_copper_bulb" => 4,
+        _ if name.ends_with("candle") => 3 * number("candles"),
+        _ if name.ends_with("candle_cake") => 3,
+        _ => 0,
+    }
+}
+
+/// Whether a block stops light completely. Full cubes do, except for the ones that are
+/// see-through.
+pub fn blocks_light(block_models: &BlockModels, block: &str) -> bool {
+    let (name, _) = split_block_state(block);
+    let name = name.trim_start_matches("minecraft:");
+    let see_through = TRANSPARENT_BLOCKS.contains(&name)
+        || name.ends_with("leaves")
+        || (name.contains("glass") && name != "tinted_glass");
+    let full_cube = block_models.element_boxes(block).iter().any(|element| {
+        let (min, max) = element.bounds();
+        let covers = min.cmple(Vec3::ZERO).all() && max.cmpge(Vec3::splat(16.0)).all();
+        element.axis_aligned && covers
+    });
+    full_cube && !see_through
+}
+
+/// Spreads light through the schematic on request, caching which blocks stop it
+#[derive(Default)]
+pub struct LightEstimator {
+    opaque: HashMap<String, bool>,
+    /// Lowest Y of each column from which the sky is visible
+    sky_heights: Vec<i32>,
+}
+
+impl LightEstimator {
+    fn is_opaque(&mut self, block_models: &BlockModels, block: &str) -> bool {
+        if let Some(&opaque) = self.opaque.get(block) {
+            return opaque;
+        }
+        let opaque = blocks_light(block_models, block);
+        self.opaque.insert(block.to_string(), opaque);
+        opaque
+    }
+
+    fn sky_exposed(&self, size: IVec3, pos: IVec3) -> bool {
+        if pos.x < 0 || pos.z < 0 || pos.x >= size.x || pos.z >= size.z {
+            return true;
+        }
+        pos.y >= self.sky_heights[(pos.x + pos.z * size.x) as usize]
+    }
+
+    /// Sky and block light at a position relative to the schematic origin
+    pub fn light_at(
+        &mut self,
+        block_world: &BlockWorld,
+        block_models: &BlockModels,
+        has_sky: bool,
+        pos: IVec3,
+    ) -> (u8, u8) {
+        let (sx, sy, sz) = block_world.blocks.size();
+        let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+        let block_at = |pos: IVec3| {
+            let inside = pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size).all();
+            inside.then(|| {
+                block_world
+                    .blocks
+                    .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize)
+            })
+        };
+
+        if self.sky_heights.is_empty() {
+            for z in 0..size.z {
+                for x in 0..size.x {
+                    let height = (0..size.y)
+                        .rev()
+                        .find(|&y| {
+                            let block = block_at(IVec3::new(x, y, z)).unwrap_or_default();
+                            self.is_opaque(block_models, block)
+                        })
+                        .map_or(0, |y| y + 1);
+                    self.sky_heights.push(height);
+                }
+            }
+        }
+
+        // Light drops by one for every block it travels, so a search outwards from the position
+        // finds the strongest sources within reach
+        let mut sky = 0;
+        let mut light = 0;
+        let mut visited = HashSet::new();
+        visited.insert(pos);
+        let mut queue = VecDeque::from([(pos, 0)]);
+        while let Some((cell, distance)) = queue.pop_front() {
+            if has_sky && self.sky_exposed(size, cell) {
+                sky = sky.max(MAX_LIGHT - distance);
+            }
+            if let Some(block) = block_at(cell) {
+                light = light.max(luminance(block).saturating_sub(distance));
+            }
+            if distance == MAX_LIGHT {
+                continue;
+            }
+            for dir in [
+                IVec3::X,
+                IVec3::NEG_X,
+                IVec3::Y,
+                IVec3::NEG_Y,
+                IVec3::Z,
+                IVec3::NEG_Z,
+            ] {
+                let next = cell + dir;
+                if !visited.insert(next) {
+                    continue;
+                }
+                match block_at(next) {
+                    // Opaque blocks can still give off light themselves, like glowstone
+                    Some(block) if self.is_opaque(block_models, block) => {
+                        light = light.max(luminance(block).saturating_sub(distance + 1));
+                    }
+                    _ => queue.push_back((next, distance + 1)),
+                }
+            }
+        }
+        (sky, light)
+    }
+}
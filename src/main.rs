@@ -1,8 +1,36 @@
+mod banner;
 mod block;
 mod cli;
 mod debug_menu;
+mod dye;
+mod editor;
+mod entity;
+mod environment;
+mod icon;
+mod inspector;
+mod item;
+mod lang;
+mod light;
+mod material_list;
+mod model;
+mod origin;
+mod outline;
+mod picking;
+mod property_view;
+mod query;
+mod replace;
 mod resources;
+mod schematic;
+mod search;
+mod section;
+mod selection;
+mod shadows;
+mod sign;
+mod time_of_day;
+mod transform;
+mod xray;
 
+use banner::BannerPlugin;
 use bevy::core_pipeline::experimental::taa::TemporalAntiAliasBundle;
 use bevy::diagnostic::{
     EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, SystemInformationDiagnosticsPlugin,
@@ -19,12 +47,36 @@ use bevy_fly_camera::{FlyCamera, FlyCameraPlugin};
 use block::{BlockBundle, BlockPalette, BlockPlugin};
 use color_eyre::Result;
 use debug_menu::McDebugMenuPlugin;
+use editor::{Editor, EditorPlugin};
+use entity::SchematicEntityPlugin;
+use environment::{Dimension, EnvironmentPlugin};
+use icon::IconPlugin;
+use inspector::InspectorPlugin;
+use item::ItemModels;
 use iyes_perf_ui::prelude::*;
+use lang::Lang;
+use material_list::{MaterialList, MaterialListPlugin};
 use mc_schems::{Blocks, Schematic};
+use origin::OriginPlugin;
+use outline::OutlinePlugin;
+use picking::PickingPlugin;
+use property_view::PropertyViewPlugin;
+use query::BlockTags;
+use replace::ReplacePlugin;
+use resources::font::McFont;
 use resources::mc_meta::{McMetaAsset, McMetaAssetLoader};
 use resources::McAssetLoaderPlugin;
+use schematic::{BlockEntity, SchematicBiomes, SchematicEntity, SchematicNbt};
+use search::SearchPlugin;
+use section::{SectionPlugin, SectionView};
+use selection::SelectionPlugin;
+use shadows::{ShadowPlugin, ShadowSettings};
+use sign::SignPlugin;
 use std::f32::consts::PI;
 use std::fs;
+use time_of_day::{TimeOfDay, TimeOfDayPlugin};
+use transform::{transform_schematic, BlockTransform, TransformPlugin};
+use xray::{Xray, XrayPlugin};
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
 enum AppLoadState {
@@ -33,36 +85,18 @@ enum AppLoadState {
     Finished,
 }
 
-fn setup_lights(mut commands: Commands, mut ambient_light: ResMut<AmbientLight>) {
-    ambient_light.brightness = 1000.0;
-    commands.spawn(DirectionalLightBundle {
-        transform: Transform::from_xyz(0.0, 20.0, 20.0).looking_at(Vec3::ZERO, Vec3::Y),
-        directional_light: DirectionalLight {
-            illuminance: 1000.0,
-            ..default()
-        },
-        ..default()
-    });
-    commands.spawn(DirectionalLightBundle {
-        transform: Transform::from_xyz(0.0, 20.0, -20.0).looking_at(Vec3::ZERO, Vec3::Y),
-        directional_light: DirectionalLight {
-            illuminance: 1000.0,
-            ..default()
-        },
-        ..default()
-    });
-}
+/// Parent of everything placed in schematic coordinates
+#[derive(Component)]
+struct SchematicRoot;
 
 fn setup(
     mut commands: Commands,
-    block_world: Res<BlockWorld>,
+    mut block_world: ResMut<BlockWorld>,
     mut block_palette: ResMut<BlockPalette>,
 ) {
+    // Block positions in the schematic keep Minecraft's axes, with +X east, +Y up and +Z south
     let world_parent = commands
-        .spawn(SpatialBundle {
-            transform: Transform::from_rotation(Quat::from_rotation_y(PI)),
-            ..default()
-        })
+        .spawn((SchematicRoot, SpatialBundle::default()))
         .id();
 
     let (sx, sy, sz) = block_world.blocks.size();
@@ -75,12 +109,16 @@ fn setup(
                 }
 
                 let idx = block_palette.get_or_add(block);
-                commands
+                let entity = commands
                     .spawn(BlockBundle::new(
                         idx,
                         IVec3::new(x as i32, y as i32, z as i32),
                     ))
-                    .set_parent(world_parent);
+                    .set_parent(world_parent)
+                    .id();
+                block_world
+                    .entities
+                    .insert(UVec3::new(x as u32, y as u32, z as u32), entity);
             }
         }
     }
@@ -89,21 +127,42 @@ fn setup(
 #[derive(Component)]
 struct McCamera;
 
-fn setup_camera(mut commands: Commands) {
-    commands
-        .spawn(Camera3dBundle::default())
+/// Problems found while loading the schematic and assets, which happens before the logger is
+/// set up
+#[derive(Resource, Default)]
+struct StartupWarnings(Vec<String>);
+
+impl StartupWarnings {
+    fn push(&mut self, warning: String) {
+        self.0.push(warning);
+    }
+}
+
+fn log_startup_warnings(warnings: Res<StartupWarnings>) {
+    for warning in &warnings.0 {
+        warn!("{}", warning);
+    }
+}
+
+fn setup_camera(mut commands: Commands, dimension: Res<Dimension>) {
+    // Start at the first block looking south, across the schematic
+    let mut camera = commands.spawn(Camera3dBundle {
+        transform: Transform::from_rotation(Quat::from_rotation_y(PI)),
+        ..default()
+    });
+    camera
         .insert(ScreenSpaceAmbientOcclusionBundle::default())
         .insert(TemporalAntiAliasBundle::default())
         .insert(McCamera)
-        .insert(AtmosphereCamera::default())
+        .insert(dimension.fog_settings())
         .insert(FlyCamera {
             enabled: false,
+            yaw: 180.0,
             ..default()
         });
-    commands.insert_resource(AtmosphereModel::new(Nishita {
-        sun_position: Vec3::new(1.0, 1.0, 0.0),
-        ..default()
-    }));
+    if dimension.has_sky_cycle() {
+        camera.insert(AtmosphereCamera::default());
+    }
     commands.spawn(PerfUiCompleteBundle::default());
 }
 
@@ -131,77 +190,209 @@ fn mouse_grab(
 #[derive(Resource)]
 struct BlockWorld {
     blocks: Blocks,
+    block_entities: HashMap<IVec3, BlockEntity>,
+    /// Entities stored in the schematic, like item frames and armor stands
+    schematic_entities: Vec<SchematicEntity>,
+    /// World position of the first block
+    offset: IVec3,
+    /// World position the schematic was copied from
+    origin: IVec3,
+    biomes: Option<SchematicBiomes>,
+    /// Version of the game the schematic was saved in, written back when saving edits
+    data_version: Option<i32>,
     entities: HashMap<UVec3, Entity>,
 }
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let cli = cli::parse();
-    let schematic = Schematic::deserialize(&fs::read(cli.schem_file)?)?;
+    let mut warnings = StartupWarnings::default();
+    let schem_bytes = fs::read(&cli.schem_file)?;
+    let mut schematic = Schematic::deserialize(&schem_bytes)?;
+    let mut schematic_nbt = SchematicNbt::read(&schem_bytes)?;
+    let transform = BlockTransform {
+        mirror: cli.mirror,
+        rotation: cli.rotate,
+    };
+    if !transform.is_identity() {
+        schematic.blocks = transform_schematic(&schematic.blocks, &mut schematic_nbt, transform);
+    }
+
+    let lang = Lang::load(&cli.locale, &mut warnings);
+    let material_list = MaterialList::count(&schematic.blocks, &lang);
+
+    // Queries and exports from the command line are done without opening a window
+    if cli.query.is_some() || cli.export_materials.is_some() {
+        for warning in &warnings.0 {
+            eprintln!("Warning: {}", warning);
+        }
+        if let Some(query) = &cli.query {
+            let offset = cli
+                .coordinates
+                .offset_between(schematic_nbt.offset, schematic_nbt.origin);
+            let tags = BlockTags::new(cli.datapacks);
+            query::print_matches(query, &tags, &schematic.blocks, offset)?;
+        }
+        if let Some(path) = &cli.export_materials {
+            material_list.export(path)?;
+        }
+        return Ok(());
+    }
 
     let asset_pack = resources::asset_pack::load_asset_pack()?;
-    let models = block::get_block_models_for(&asset_pack, &schematic)?;
-
-    App::new()
-        .add_plugins((
-            DefaultPlugins
-                .set(RenderPlugin {
-                    render_creation: RenderCreation::Automatic(WgpuSettings {
-                        // WARN this is a native only feature. It will not work with webgl or webgpu
-                        features: WgpuFeatures::POLYGON_MODE_LINE,
-                        ..default()
-                    }),
+    let mut models = block::get_block_models_for(&asset_pack, &schematic)?;
+    for block in entity::entity_block_models(&schematic_nbt.entities) {
+        models.insert(&asset_pack, &block)?;
+    }
+    let mut item_models = ItemModels::default();
+    for item in entity::entity_items(&schematic_nbt.entities) {
+        if let Err(err) = item_models.insert(&asset_pack, &mut models, &item) {
+            warnings.push(format!("Could not load item model for {}: {}", item, err));
+        }
+    }
+    // The material list shows the icons of its items, which are drawn from their models
+    for item in material_list.items() {
+        if let Err(err) = item_models.insert(&asset_pack, &mut models, item) {
+            warnings.push(format!("Could not load item model for {}: {}", item, err));
+        }
+    }
+    // The font is only needed for the text on signs, which is left out without it
+    let font = match McFont::load() {
+        Ok(font) => Some(font),
+        Err(err) => {
+            warnings.push(format!("Could not load the font for sign text: {}", err));
+            None
+        }
+    };
+
+    let xray = Xray::load(cli.xray_presets, &mut warnings);
+
+    let (sx, sy, sz) = schematic.blocks.size();
+    let mut shadow_settings =
+        ShadowSettings::for_bounds(Vec3::new(sx as f32, sy as f32, sz as f32));
+    if let Some(num_cascades) = cli.shadow_cascades {
+        shadow_settings.num_cascades = num_cascades;
+    }
+    if let Some(maximum_distance) = cli.shadow_distance {
+        shadow_settings.maximum_distance = maximum_distance;
+    }
+    if let Some(depth_bias) = cli.shadow_bias {
+        shadow_settings.depth_bias = depth_bias;
+    }
+
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    // WARN this is a native only feature. It will not work with webgl or webgpu
+                    features: WgpuFeatures::POLYGON_MODE_LINE,
                     ..default()
-                })
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "MC Renderer".into(),
-                        present_mode: if cli.no_vsync {
-                            PresentMode::AutoNoVsync
-                        } else {
-                            PresentMode::AutoVsync
-                        },
-                        ..default()
-                    }),
+                }),
+                ..default()
+            })
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "MC Renderer".into(),
+                    present_mode: if cli.no_vsync {
+                        PresentMode::AutoNoVsync
+                    } else {
+                        PresentMode::AutoVsync
+                    },
                     ..default()
                 }),
-            // You need to add this plugin to enable wireframe rendering
-            WireframePlugin,
-        ))
-        // Wireframes can be configured with this resource. This can be changed at runtime.
-        .insert_resource(WireframeConfig {
-            // The global wireframe config enables drawing of wireframes on every mesh,
-            // except those with `NoWireframe`. Meshes with `Wireframe` will always have a
-            // wireframe, regardless of the global configuration.
-            global: cli.wireframe,
-            // Controls the default color of all wireframes. Used as the default color for global
-            // wireframes. Can be changed per mesh using the `WireframeColor` component.
-            default_color: Color::WHITE,
-        })
-        .insert_resource(Msaa::Off)
-        .add_plugins((McDebugMenuPlugin, FlyCameraPlugin))
-        // Perf UI
-        .add_plugins((
-            PerfUiPlugin,
-            FrameTimeDiagnosticsPlugin,
-            EntityCountDiagnosticsPlugin,
-            SystemInformationDiagnosticsPlugin,
-            AtmospherePlugin,
-            McAssetLoaderPlugin,
-            BlockPlugin,
-        ))
-        .init_state::<AppLoadState>()
-        .init_asset::<McMetaAsset>()
-        .init_asset_loader::<McMetaAssetLoader>()
-        .insert_resource(BlockWorld {
-            blocks: schematic.blocks,
-            entities: HashMap::new(),
-        })
-        .insert_resource(models)
-        .add_systems(OnEnter(AppLoadState::Finished), setup)
-        .add_systems(Startup, (setup_camera, setup_lights))
-        .add_systems(Update, mouse_grab)
-        .run();
+                ..default()
+            }),
+        // You need to add this plugin to enable wireframe rendering
+        WireframePlugin,
+    ))
+    // Wireframes can be configured with this resource. This can be changed at runtime.
+    .insert_resource(WireframeConfig {
+        // The global wireframe config enables drawing of wireframes on every mesh,
+        // except those with `NoWireframe`. Meshes with `Wireframe` will always have a
+        // wireframe, regardless of the global configuration.
+        global: cli.wireframe,
+        // Controls the default color of all wireframes. Used as the default color for global
+        // wireframes. Can be changed per mesh using the `WireframeColor` component.
+        default_color: Color::WHITE,
+    })
+    .insert_resource(Msaa::Off)
+    .add_plugins((McDebugMenuPlugin, FlyCameraPlugin))
+    // Perf UI
+    .add_plugins((
+        PerfUiPlugin,
+        FrameTimeDiagnosticsPlugin,
+        EntityCountDiagnosticsPlugin,
+        SystemInformationDiagnosticsPlugin,
+        McAssetLoaderPlugin,
+        BlockPlugin,
+        TimeOfDayPlugin,
+        ShadowPlugin,
+        EnvironmentPlugin,
+        SignPlugin,
+        BannerPlugin,
+        SchematicEntityPlugin,
+    ))
+    // Tools for looking around the schematic
+    .add_plugins((
+        PickingPlugin,
+        OutlinePlugin,
+        InspectorPlugin,
+        OriginPlugin,
+        SectionPlugin,
+        XrayPlugin,
+        PropertyViewPlugin,
+        SearchPlugin,
+        MaterialListPlugin,
+        IconPlugin,
+        EditorPlugin,
+        SelectionPlugin,
+        TransformPlugin,
+        ReplacePlugin,
+    ))
+    .init_state::<AppLoadState>()
+    .init_asset::<McMetaAsset>()
+    .init_asset_loader::<McMetaAssetLoader>()
+    .insert_resource(BlockWorld {
+        blocks: schematic.blocks,
+        block_entities: schematic_nbt.block_entities,
+        schematic_entities: schematic_nbt.entities,
+        offset: schematic_nbt.offset,
+        origin: schematic_nbt.origin,
+        biomes: schematic_nbt.biomes,
+        data_version: schematic_nbt.data_version,
+        entities: HashMap::new(),
+    })
+    .insert_resource(models)
+    .insert_resource(item_models)
+    .insert_resource(shadow_settings)
+    .insert_resource(SectionView::for_size(IVec3::new(
+        sx as i32, sy as i32, sz as i32,
+    )))
+    .insert_resource(cli.dimension)
+    .insert_resource(cli.coordinates)
+    .insert_resource(xray)
+    .insert_resource(BlockTags::new(cli.datapacks))
+    .insert_resource(material_list)
+    .insert_resource(lang)
+    .insert_resource(Editor::new(&cli.schem_file))
+    .insert_resource(TimeOfDay {
+        ticks: cli.time,
+        cycle: cli.day_cycle,
+        ..default()
+    })
+    .add_systems(OnEnter(AppLoadState::Finished), setup)
+    .insert_resource(warnings)
+    .add_systems(Startup, (setup_camera, log_startup_warnings))
+    .add_systems(Update, mouse_grab);
+    if let Some(font) = font {
+        app.insert_resource(font);
+    }
+    // Only the Overworld uses the atmosphere, other dimensions set up their own sky
+    if cli.dimension.has_sky_cycle() {
+        app.add_plugins(AtmospherePlugin);
+    }
+    app.run();
 
     Ok(())
 }
@@ -0,0 +1,432 @@
+//! Bill of materials for the schematic, counting the items needed to build it rather than block
+//! states, so slabs, doors and beds count the way they're placed and wire counts as redstone
+//! dust. The list is shown in a window and can be exported as CSV or JSON.
+
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContexts};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use mc_schems::Blocks;
+use serde::Serialize;
+
+use crate::block::{parse_props, split_block_state};
+use crate::icon::BlockIcons;
+use crate::lang::Lang;
+use crate::query::BlockTags;
+use crate::search::Search;
+use crate::{AppLoadState, BlockWorld};
+
+/// Slots in a shulker box
+const SHULKER_BOX_SLOTS: u64 = 27;
+
+/// Blocks placed from an item of a different name
+const BLOCK_ITEMS: &[(&str, &str)] = &[
+    ("redstone_wire", "redstone"),
+    ("tripwire", "string"),
+    ("wall_torch", "torch"),
+    ("soul_wall_torch", "soul_torch"),
+    ("redstone_wall_torch", "redstone_torch"),
+    ("wheat", "wheat_seeds"),
+    ("carrots", "carrot"),
+    ("potatoes", "potato"),
+    ("beetroots", "beetroot_seeds"),
+    ("melon_stem", "melon_seeds"),
+    ("attached_melon_stem", "melon_seeds"),
+    ("pumpkin_stem", "pumpkin_seeds"),
+    ("attached_pumpkin_stem", "pumpkin_seeds"),
+    ("cocoa", "cocoa_beans"),
+    ("sweet_berry_bush", "sweet_berries"),
+    ("torchflower_crop", "torchflower_seeds"),
+    ("pitcher_crop", "pitcher_pod"),
+    ("kelp_plant", "kelp"),
+    ("cave_vines", "glow_berries"),
+    ("cave_vines_plant", "glow_berries"),
+    ("twisting_vines_plant", "twisting_vines"),
+    ("weeping_vines_plant", "weeping_vines"),
+    ("bamboo_sapling", "bamboo"),
+    ("big_dripleaf_stem", "big_dripleaf"),
+    ("tall_seagrass", "seagrass"),
+    ("water_cauldron", "cauldron"),
+    ("lava_cauldron", "cauldron"),
+    ("powder_snow_cauldron", "cauldron"),
+    ("powder_snow", "powder_snow_bucket"),
+];
+
+/// Blocks that can't be placed from an item
+const NO_ITEM: &[&str] = &[
+    "air",
+    "cave_air",
+    "void_air",
+    "fire",
+    "soul_fire",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+    "piston_head",
+    "moving_piston",
+    "bubble_column",
+    "frosted_ice",
+];
+
+/// Properties that count several items placed in one block, like candles and sea pickles
+const COUNT_PROPERTIES: &[&str] = &[
+    "candles",
+    "pickles",
+    "eggs",
+    "layers",
+    "flower_amount",
+    "segment_amount",
+];
+
+/// Items needed to place a block state, with their counts
+pub fn block_items(block: &str) -> Vec<(String, u64)> {
+    let (name, props) = split_block_state(block);
+    let props = parse_props(props);
+    let Some(id) = name.strip_prefix("minecraft:") else {
+        return vec![(name.to_string(), 1)];
+    };
+    let item = |id: &str| format!("minecraft:{}", id);
+
+    // Blocks that take up two spaces are counted by their lower half or their foot
+    let upper_half = props.get("half") == Some(&"upper");
+    if NO_ITEM.contains(&id) || upper_half || props.get("part") == Some(&"head") {
+        return Vec::new();
+    }
+    if id == "water" || id == "lava" {
+        // Only source blocks can be placed
+        return match props.get("level") {
+            Some(&"0") | None => vec![(item(&format!("{}_bucket", id)), 1)],
+            Some(_) => Vec::new(),
+        };
+    }
+    if let Some(plant) = id.strip_prefix("potted_") {
+        let plant = match plant {
+            "azalea_bush" => "azalea",
+            "flowering_azalea_bush" => "flowering_azalea",
+            plant => plant,
+        };
+        return vec![(item("flower_pot"), 1), (item(plant), 1)];
+    }
+    if let Some(candle) = id.strip_suffix("_cake").filter(|id| id.ends_with("candle")) {
+        return vec![(item("cake"), 1), (item(candle), 1)];
+    }
+
+    let count = if props.get("type") == Some(&"double") {
+        2
+    } else {
+        COUNT_PROPERTIES
+            .iter()
+            .find_map(|prop| props.get(prop)?.parse().ok())
+            .unwrap_or(1)
+    };
+    let wall_variant = ["_sign", "_banner", "_head", "_skull", "_fan"]
+        .iter()
+        .any(|suffix| id.ends_with(suffix));
+    let id = match BLOCK_ITEMS.iter().find(|(block, _)| *block == id) {
+        Some((_, item)) => item.to_string(),
+        // Blocks on walls like `oak_wall_sign` are placed from the item without `wall`
+        None if wall_variant => id.replacen("_wall_", "_", 1),
+        None => id.to_string(),
+    };
+    vec![(item(&id), count)]
+}
+
+/// Largest stack of an item, for the items blocks are placed from
+fn stack_size(item: &str) -> u64 {
+    let id = item.trim_start_matches("minecraft:");
+    let single = ["_bed", "shulker_box", "_bucket"]
+        .iter()
+        .any(|suffix| id.ends_with(suffix));
+    if single || id == "cake" {
+        1
+    } else if id.ends_with("_sign") || id.ends_with("_banner") {
+        16
+    } else {
+        64
+    }
+}
+
+/// One row of the list
+struct Material {
+    item: String,
+    /// Display name of the item
+    name: String,
+    count: u64,
+    /// Names of the blocks placed from the item
+    blocks: Vec<String>,
+}
+
+impl Material {
+    fn stack_size(&self) -> u64 {
+        stack_size(&self.item)
+    }
+
+    /// Stacks needed to carry the item
+    fn stacks(&self) -> u64 {
+        self.count.div_ceil(self.stack_size())
+    }
+
+    fn shulker_boxes(&self) -> u64 {
+        self.stacks().div_ceil(SHULKER_BOX_SLOTS)
+    }
+
+    /// The count split into full shulker boxes, full stacks and the rest
+    fn in_stacks(&self) -> String {
+        let stack = self.stack_size();
+        let box_size = stack * SHULKER_BOX_SLOTS;
+        let parts = [
+            (self.count / box_size, "SB"),
+            (self.count % box_size / stack, "st"),
+            (self.count % stack, ""),
+        ];
+        let parts: Vec<_> = parts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, unit)| format!("{} {}", count, unit).trim_end().to_string())
+            .collect();
+        parts.join(" + ")
+    }
+}
+
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    item: &'a str,
+    name: &'a str,
+    count: u64,
+    stack_size: u64,
+    stacks: u64,
+    shulker_boxes: u64,
+}
+
+#[derive(Resource)]
+pub struct MaterialList {
+    materials: Vec<Material>,
+}
+
+impl MaterialList {
+    pub fn count(blocks: &Blocks, lang: &Lang) -> Self {
+        let mut states: HashMap<&str, u64> = HashMap::new();
+        let (sx, sy, sz) = blocks.size();
+        for x in 0..sx {
+            for y in 0..sy {
+                for z in 0..sz {
+                    *states.entry(blocks.get_block_at(x, y, z)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut items: HashMap<String, (u64, HashSet<&str>)> = HashMap::new();
+        for (state, count) in states {
+            for (item, per_block) in block_items(state) {
+                let (total, blocks) = items.entry(item).or_default();
+                *total += per_block * count;
+                blocks.insert(split_block_state(state).0);
+            }
+        }
+        let mut materials: Vec<_> = items
+            .into_iter()
+            .map(|(item, (count, blocks))| {
+                let mut blocks: Vec<_> = blocks.into_iter().map(String::from).collect();
+                blocks.sort();
+                Material {
+                    name: lang.item_name(&item),
+                    item,
+                    count,
+                    blocks,
+                }
+            })
+            .collect();
+        materials.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.item.cmp(&b.item)));
+        Self { materials }
+    }
+
+    /// Ids of all items on the list
+    pub fn items(&self) -> impl Iterator<Item = &str> {
+        self.materials.iter().map(|material| material.item.as_str())
+    }
+
+    fn rows(&self) -> impl Iterator<Item = ExportRow<'_>> + '_ {
+        self.materials.iter().map(|material| ExportRow {
+            item: &material.item,
+            name: &material.name,
+            count: material.count,
+            stack_size: material.stack_size(),
+            stacks: material.stacks(),
+            shulker_boxes: material.shulker_boxes(),
+        })
+    }
+
+    /// Writes the list to a `.csv` or `.json` file, depending on its extension
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => {
+                let mut csv = "item,name,count,stack_size,stacks,shulker_boxes\n".to_string();
+                for row in self.rows() {
+                    // Names are quoted since translations may contain commas
+                    let name = format!("\"{}\"", row.name.replace('"', "\"\""));
+                    csv += &format!(
+                        "{},{},{},{},{},{}\n",
+                        row.item, name, row.count, row.stack_size, row.stacks, row.shulker_boxes
+                    );
+                }
+                csv
+            }
+            Some("json") => serde_json::to_string_pretty(&self.rows().collect::<Vec<_>>())?,
+            _ => bail!("Materials can only be exported to .csv or .json files"),
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MaterialSort {
+    Item,
+    Count,
+}
+
+impl MaterialSort {
+    const HEADERS: [(MaterialSort, &'static str); 2] =
+        [(MaterialSort::Item, "Item"), (MaterialSort::Count, "Count")];
+}
+
+struct MaterialsWindow {
+    sort: MaterialSort,
+    descending: bool,
+    /// Export path without the extension
+    export_path: String,
+    status: Option<String>,
+}
+
+impl Default for MaterialsWindow {
+    fn default() -> Self {
+        Self {
+            sort: MaterialSort::Count,
+            descending: true,
+            export_path: "materials".to_string(),
+            status: None,
+        }
+    }
+}
+
+fn materials_window(
+    mut contexts: EguiContexts,
+    mut window: Local<MaterialsWindow>,
+    list: Res<MaterialList>,
+    mut search: ResMut<Search>,
+    tags: Res<BlockTags>,
+    block_world: Res<BlockWorld>,
+    icons: Res<BlockIcons>,
+) {
+    let window = &mut *window;
+    egui::Window::new("Materials")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let total: u64 = list.materials.iter().map(|material| material.count).sum();
+            ui.label(format!("{} items of {} kinds", total, list.materials.len()));
+
+            let mut order: Vec<_> = list.materials.iter().collect();
+            match window.sort {
+                MaterialSort::Item => order.sort_by(|a, b| a.name.cmp(&b.name)),
+                MaterialSort::Count => order.sort_by_key(|material| material.count),
+            }
+            if window.descending {
+                order.reverse();
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("materials_grid")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (sort, name) in MaterialSort::HEADERS {
+                                let arrow = match (window.sort == sort, window.descending) {
+                                    (false, _) => "",
+                                    (true, false) => " ⏶",
+                                    (true, true) => " ⏷",
+                                };
+                                let header = format!("{}{}", name, arrow);
+                                if ui.selectable_label(window.sort == sort, header).clicked() {
+                                    window.descending = window.sort != sort || !window.descending;
+                                    window.sort = sort;
+                                }
+                            }
+                            ui.label("Stacks");
+                            ui.end_row();
+
+                            // Clicking an item highlights the blocks placed from it
+                            for material in order {
+                                let clicked = ui
+                                    .horizontal(|ui| {
+                                        if let Some(icon) = icons.item_icon(&material.item) {
+                                            ui.add(icon);
+                                        }
+                                        let response = ui.selectable_label(false, &material.name);
+                                        response.on_hover_text(&material.item).clicked()
+                                    })
+                                    .inner;
+                                if clicked {
+                                    search.set_query(
+                                        material.blocks.join(" "),
+                                        &tags,
+                                        &block_world,
+                                    );
+                                }
+                                ui.label(material.count.to_string());
+                                ui.label(material.in_stacks());
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut window.export_path);
+                for extension in ["csv", "json"] {
+                    if ui
+                        .button(format!("Export {}", extension.to_uppercase()))
+                        .clicked()
+                    {
+                        let path = Path::new(&window.export_path).with_extension(extension);
+                        window.status = Some(match list.export(&path) {
+                            Ok(()) => format!("Exported to {}", path.display()),
+                            Err(err) => format!("Could not export: {}", err),
+                        });
+                    }
+                }
+            });
+            if let Some(status) = &window.status {
+                ui.label(status);
+            }
+        });
+}
+
+/// Counts the list again once the schematic has been edited
+fn recount_materials(
+    mut list: ResMut<MaterialList>,
+    block_world: Res<BlockWorld>,
+    lang: Res<Lang>,
+) {
+    if block_world.is_changed() && !block_world.is_added() {
+        *list = MaterialList::count(&block_world.blocks, &lang);
+    }
+}
+
+pub struct MaterialListPlugin;
+
+impl Plugin for MaterialListPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (recount_materials, materials_window)
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
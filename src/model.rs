@@ -0,0 +1,291 @@
+//! Building blocks for models that are hardcoded instead of loaded from JSON, like block
+//! entities and entities.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+use crate::resources::textures::TextureAtlas;
+
+/// A box of an entity model, textured with the vanilla box UV layout
+pub struct Cube {
+    tex_offset: Vec2,
+    from: Vec3,
+    size: Vec3,
+    /// Amount the box grows by in every direction, without affecting its texture layout
+    grow: f32,
+    /// Whether the texture is flipped horizontally, like on the left limbs of vanilla models
+    mirror: bool,
+}
+
+impl Cube {
+    pub fn new(u: f32, v: f32, from: [f32; 3], size: [f32; 3]) -> Self {
+        Self {
+            tex_offset: Vec2::new(u, v),
+            from: Vec3::from_array(from),
+            size: Vec3::from_array(size),
+            grow: 0.0,
+            mirror: false,
+        }
+    }
+
+    pub fn grow(self, grow: f32) -> Self {
+        Self { grow, ..self }
+    }
+
+    pub fn mirror(self) -> Self {
+        Self {
+            mirror: true,
+            ..self
+        }
+    }
+
+    /// Returns the four corners and pixel UV rect `[u1, v1, u2, v2]` of each face. The first
+    /// corner gets `(u2, v1)`, then `(u1, v1)`, `(u1, v2)` and `(u2, v2)`.
+    fn faces(&self) -> [([Vec3; 4], [f32; 4], Vec3); 6] {
+        let min = self.from - self.grow;
+        let max = self.from + self.size + self.grow;
+        let [u, v] = self.tex_offset.to_array();
+        let [w, h, d] = self.size.to_array();
+
+        let v0 = Vec3::new(min.x, min.y, min.z);
+        let v1 = Vec3::new(max.x, min.y, min.z);
+        let v2 = Vec3::new(max.x, max.y, min.z);
+        let v3 = Vec3::new(min.x, max.y, min.z);
+        let v4 = Vec3::new(min.x, min.y, max.z);
+        let v5 = Vec3::new(max.x, min.y, max.z);
+        let v6 = Vec3::new(max.x, max.y, max.z);
+        let v7 = Vec3::new(min.x, max.y, max.z);
+
+        let mut faces = [
+            ([v5, v4, v0, v1], [u + d, v, u + d + w, v + d], Vec3::NEG_Y),
+            (
+                [v2, v3, v7, v6],
+                [u + d + w, v + d, u + d + w + w, v],
+                Vec3::Y,
+            ),
+            ([v0, v4, v7, v3], [u, v + d, u + d, v + d + h], Vec3::NEG_X),
+            (
+                [v1, v0, v3, v2],
+                [u + d, v + d, u + d + w, v + d + h],
+                Vec3::NEG_Z,
+            ),
+            (
+                [v5, v1, v2, v6],
+                [u + d + w, v + d, u + d + w + d, v + d + h],
+                Vec3::X,
+            ),
+            (
+                [v4, v5, v6, v7],
+                [u + d + w + d, v + d, u + d + w + d + w, v + d + h],
+                Vec3::Z,
+            ),
+        ];
+
+        if self.mirror {
+            // The east and west faces trade places, and every face is flipped
+            let west = faces[2].1;
+            faces[2].1 = faces[4].1;
+            faces[4].1 = west;
+            for (_, [u1, _, u2, _], _) in &mut faces {
+                std::mem::swap(u1, u2);
+            }
+        }
+        faces
+    }
+}
+
+/// A group of cubes sharing a transform, in pixels relative to the block
+pub struct Part {
+    pub transform: Mat4,
+    pub cubes: Vec<Cube>,
+}
+
+/// A model made of boxes, in the style of vanilla entity models. The part transforms include
+/// the renderer's transforms, so the model is in pixels relative to whatever it belongs to.
+pub struct EntityModel {
+    /// Texture location, like `entity/chest/normal`
+    pub texture: String,
+    pub parts: Vec<Part>,
+}
+
+impl EntityModel {
+    /// Appends the geometry of this model to a block mesh, returning whether the texture has
+    /// transparency.
+    pub fn append_geometry(
+        &self,
+        atlas: &TextureAtlas,
+        positions: &mut Vec<[f32; 3]>,
+        normals: &mut Vec<[f32; 3]>,
+        uvs: &mut Vec<[f32; 2]>,
+        indices: &mut Vec<u32>,
+    ) -> bool {
+        let texture = atlas.get_tex_details(&self.texture);
+        self.append_geometry_with(
+            |u, v| texture.get_atlas_uvs(u / texture.size.x, v / texture.size.y),
+            positions,
+            normals,
+            uvs,
+            indices,
+        );
+        texture.has_transparency
+    }
+
+    /// Creates a mesh of just this model, textured from the atlas
+    pub fn atlas_mesh(&self, atlas: &TextureAtlas) -> Mesh {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+        self.append_geometry(atlas, &mut positions, &mut normals, &mut uvs, &mut indices);
+        build_mesh(positions, normals, uvs, indices)
+    }
+
+    /// Creates a mesh of just this model, for models with a texture of their own instead of
+    /// one from the atlas. `texture_size` is the size of that texture in pixels.
+    pub fn mesh(&self, texture_size: Vec2) -> Mesh {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+        self.append_geometry_with(
+            |u, v| [u / texture_size.x, v / texture_size.y],
+            &mut positions,
+            &mut normals,
+            &mut uvs,
+            &mut indices,
+        );
+
+        build_mesh(positions, normals, uvs, indices)
+    }
+
+    /// Applies a transform on top of every part
+    pub fn transformed(mut self, transform: Mat4) -> Self {
+        for part in &mut self.parts {
+            part.transform = transform * part.transform;
+        }
+        self
+    }
+
+    /// Appends the geometry, mapping texture coordinates in pixels to UVs with `map_uv`
+    fn append_geometry_with(
+        &self,
+        map_uv: impl Fn(f32, f32) -> [f32; 2],
+        positions: &mut Vec<[f32; 3]>,
+        normals: &mut Vec<[f32; 3]>,
+        uvs: &mut Vec<[f32; 2]>,
+        indices: &mut Vec<u32>,
+    ) {
+        for part in &self.parts {
+            let normal_mat = Mat3::from_mat4(part.transform).inverse().transpose();
+            for cube in &part.cubes {
+                for (corners, [u1, v1, u2, v2], normal) in cube.faces() {
+                    let index_base = positions.len() as u32;
+                    for x in [0, 1, 2, 2, 3, 0] {
+                        indices.push(x + index_base);
+                    }
+
+                    let normal = (normal_mat * normal).normalize_or_zero();
+                    let corner_uvs = [[u2, v1], [u1, v1], [u1, v2], [u2, v2]];
+                    for (corner, [u, v]) in corners.into_iter().zip(corner_uvs) {
+                        positions.push(part.transform.transform_point3(corner).to_array());
+                        normals.push(normal.to_array());
+                        uvs.push(map_uv(u, v));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn build_mesh(
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// The rotation vanilla associates with a horizontal facing, in degrees
+pub fn y_rot(facing: &str) -> f32 {
+    match facing {
+        "west" => 90.0,
+        "north" => 180.0,
+        "east" => 270.0,
+        _ => 0.0,
+    }
+}
+
+/// The rotation of a model part, applied in the same order as vanilla does
+pub fn part_rotation(x: f32, y: f32, z: f32) -> Mat4 {
+    Mat4::from_quat(Quat::from_euler(EulerRot::ZYX, z, y, x))
+}
+
+/// A mesh made of quads with per vertex colors
+#[derive(Default)]
+pub struct QuadMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl QuadMesh {
+    /// Adds a quad facing `normal`, with the given half extents along its horizontal and
+    /// vertical axes. `uv` is `[u1, v1, u2, v2]`, with `v1` at the top.
+    pub fn quad(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        half_size: Vec2,
+        uv: [f32; 4],
+        color: Color,
+    ) {
+        let (u, v) = if normal.y == 0.0 {
+            (Vec3::Y.cross(normal), Vec3::Y)
+        } else {
+            (Vec3::X, Vec3::NEG_Z * normal.y)
+        };
+        let u = u * half_size.x;
+        let v = v * half_size.y;
+        let [u1, v1, u2, v2] = uv;
+
+        let index_base = self.positions.len() as u32;
+        for x in [0, 1, 2, 2, 3, 0] {
+            self.indices.push(x + index_base);
+        }
+        let corners = [
+            (center - u - v, [u1, v2]),
+            (center + u - v, [u2, v2]),
+            (center + u + v, [u2, v1]),
+            (center - u + v, [u1, v1]),
+        ];
+        for (corner, uv) in corners {
+            self.positions.push(corner.to_array());
+            self.normals.push(normal.to_array());
+            self.uvs.push(uv);
+            self.colors.push(color.to_linear().to_f32_array());
+        }
+    }
+
+    pub fn into_mesh(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
@@ -0,0 +1,98 @@
+//! Markers for the schematic's bounding box and the origin WorldEdit copied it from, and the
+//! setting for which of the two positions are shown relative to.
+
+use bevy::prelude::*;
+use clap::ValueEnum;
+
+use crate::{AppLoadState, BlockWorld, SchematicRoot};
+
+/// What coordinates shown in the interface are relative to
+#[derive(Resource, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateSpace {
+    /// Positions in the world the schematic was saved from
+    #[default]
+    World,
+    /// Positions relative to the origin the schematic is pasted at
+    Origin,
+}
+
+impl CoordinateSpace {
+    /// What to add to a position in the schematic to get a position in this space
+    pub fn offset(self, block_world: &BlockWorld) -> IVec3 {
+        self.offset_between(block_world.offset, block_world.origin)
+    }
+
+    /// The offset for a schematic whose first block is at `offset` and which was copied from
+    /// `origin`, in world positions
+    pub fn offset_between(self, offset: IVec3, origin: IVec3) -> IVec3 {
+        match self {
+            CoordinateSpace::World => offset,
+            CoordinateSpace::Origin => offset - origin,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CoordinateSpace::World => "World",
+            CoordinateSpace::Origin => "Origin",
+        }
+    }
+}
+
+/// Whether the bounding box and origin markers are drawn
+#[derive(Resource)]
+pub struct SchematicMarkers {
+    pub visible: bool,
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct MarkerGizmos;
+
+fn draw_markers(
+    mut gizmos: Gizmos<MarkerGizmos>,
+    markers: Res<SchematicMarkers>,
+    block_world: Res<BlockWorld>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    if !markers.visible {
+        return;
+    }
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let root = root.compute_matrix();
+
+    let (sx, sy, sz) = block_world.blocks.size();
+    let size = Vec3::new(sx as f32, sy as f32, sz as f32);
+    gizmos.cuboid(
+        root * Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, size / 2.0),
+        Color::WHITE,
+    );
+
+    // The origin is a block position, so mark the whole block with the axes at its corner
+    let origin = (block_world.origin - block_world.offset).as_vec3();
+    gizmos.cuboid(
+        root * Mat4::from_translation(origin + Vec3::splat(0.5)),
+        Color::srgb(1.0, 1.0, 0.0),
+    );
+    gizmos.axes(root * Mat4::from_translation(origin), 2.0);
+}
+
+pub struct OriginPlugin;
+
+impl Plugin for OriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_gizmo_config(
+            MarkerGizmos,
+            GizmoConfig {
+                line_width: 3.0,
+                ..default()
+            },
+        )
+        .insert_resource(SchematicMarkers { visible: true })
+        .add_systems(
+            Update,
+            draw_markers.run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Outline around the targeted block, like the one vanilla draws. It follows the block's model
+//! elements instead of a full cube, so it shows which part of a component is being pointed at.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::block::BlockModels;
+use crate::picking::TargetedBlock;
+use crate::{AppLoadState, BlockWorld, SchematicRoot};
+
+/// Distance in pixels from an edge at which the surrounding space is sampled
+const EDGE_EPSILON: f32 = 0.01;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct OutlineGizmos;
+
+type Edge = (Vec3, Vec3);
+
+fn box_edges(corners: [Vec3; 8]) -> [Edge; 12] {
+    // Corners are indexed by bits for x, y and z, so edges connect indices one bit apart
+    [
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ]
+    .map(|(a, b)| (corners[a], corners[b]))
+}
+
+/// Edges of the outline of a union of axis aligned boxes. Box edges are split where other
+/// boxes start or end, and a piece is kept if the space around it isn't filled evenly, which
+/// leaves out the parts that lie inside the union or on a flat part of its surface.
+fn union_edges(boxes: &[(Vec3, Vec3)]) -> Vec<Edge> {
+    let inside = |p: Vec3| {
+        boxes
+            .iter()
+            .any(|(min, max)| p.cmpgt(*min).all() && p.cmplt(*max).all())
+    };
+
+    let mut edges = Vec::new();
+    for &(min, max) in boxes {
+        let corners = std::array::from_fn(|corner| {
+            Vec3::select(
+                BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                max,
+                min,
+            )
+        });
+        for (start, end) in box_edges(corners) {
+            let axis = if start.x != end.x {
+                0
+            } else if start.y != end.y {
+                1
+            } else {
+                2
+            };
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let mut splits: Vec<f32> = boxes
+                .iter()
+                .flat_map(|(min, max)| [min[axis], max[axis]])
+                .filter(|&t| t > start[axis] && t < end[axis])
+                .chain([start[axis], end[axis]])
+                .collect();
+            splits.sort_by(f32::total_cmp);
+            splits.dedup();
+
+            for pair in splits.windows(2) {
+                let mut from = start;
+                let mut to = start;
+                from[axis] = pair[0];
+                to[axis] = pair[1];
+                let mid = (from + to) / 2.0;
+                let quadrants = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+                let filled = quadrants.map(|(du, dv)| {
+                    let mut p = mid;
+                    p[u] += du * EDGE_EPSILON;
+                    p[v] += dv * EDGE_EPSILON;
+                    inside(p)
+                });
+                let count = filled.iter().filter(|&&filled| filled).count();
+                let diagonal = count == 2 && filled[0] == filled[2];
+                if (count == 1 || count == 3 || diagonal) && !edges.contains(&(from, to)) {
+                    edges.push((from, to));
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// The outline of a block state in pixels. Boxes without volume and rotated elements can't be
+/// merged, so their edges are drawn as they are.
+fn block_outline(block_models: &BlockModels, block: &str) -> Vec<Edge> {
+    let elements = block_models.element_boxes(block);
+    // Blocks drawn by block entity renderers don't have elements
+    if elements.is_empty() {
+        return union_edges(&[(Vec3::ZERO, Vec3::splat(16.0))]);
+    }
+
+    let mut boxes = Vec::new();
+    let mut edges = Vec::new();
+    for element in &elements {
+        let (min, max) = element.bounds();
+        let flat = (max - min).min_element() < EDGE_EPSILON;
+        if element.axis_aligned && !flat {
+            boxes.push((min, max));
+        } else {
+            edges.extend(box_edges(element.corners()));
+        }
+    }
+    edges.extend(union_edges(&boxes));
+    edges
+}
+
+fn draw_outline(
+    mut gizmos: Gizmos<OutlineGizmos>,
+    mut outlines: Local<HashMap<String, Vec<Edge>>>,
+    targeted: Res<TargetedBlock>,
+    block_world: Res<BlockWorld>,
+    block_models: Res<BlockModels>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    let (Some(pos), Ok(root)) = (targeted.0, root.get_single()) else {
+        return;
+    };
+    let block = block_world
+        .blocks
+        .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+    let edges = outlines
+        .entry(block.to_string())
+        .or_insert_with(|| block_outline(&block_models, block));
+
+    let transform = root.compute_matrix()
+        * Mat4::from_translation(pos.as_vec3())
+        * Mat4::from_scale(Vec3::splat(1.0 / 16.0));
+    // Vanilla draws the outline in translucent black
+    let color = Color::srgba(0.0, 0.0, 0.0, 0.4);
+    for &(start, end) in edges.iter() {
+        gizmos.line(
+            transform.transform_point3(start),
+            transform.transform_point3(end),
+            color,
+        );
+    }
+}
+
+pub struct OutlinePlugin;
+
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_gizmo_config(
+            OutlineGizmos,
+            GizmoConfig {
+                // Keep the lines from fighting with the faces they lie on
+                depth_bias: -0.001,
+                ..default()
+            },
+        )
+        .add_systems(
+            Update,
+            draw_outline.run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Finds the block the player is pointing at, by casting a ray from the crosshair while the
+//! mouse is grabbed, or from the cursor while it's free.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use bevy_egui::EguiContexts;
+use bevy_fly_camera::FlyCamera;
+
+use crate::block::{BlockModels, SectionClip};
+use crate::section::SectionView;
+use crate::xray::{Xray, XrayAction};
+use crate::{AppLoadState, BlockWorld, McCamera, SchematicRoot};
+
+/// How far away blocks can be picked, in blocks
+const MAX_DISTANCE: f32 = 512.0;
+
+/// The block under the crosshair or cursor, in schematic coordinates
+#[derive(Resource, Default)]
+pub struct TargetedBlock(pub Option<IVec3>);
+
+/// Normal of the face of the targeted block that the ray hit, which blocks are placed against
+#[derive(Resource, Default)]
+pub struct TargetedFace(pub Option<IVec3>);
+
+/// Boxes that rays are tested against for each block state, in blocks relative to the block.
+/// These are the bounds of the model elements, so thin blocks like redstone dust and repeaters
+/// are only hit where they're drawn.
+#[derive(Default)]
+struct BlockShapes(HashMap<String, Vec<(Vec3, Vec3)>>);
+
+impl BlockShapes {
+    fn get(&mut self, block_models: &BlockModels, block: &str) -> &[(Vec3, Vec3)] {
+        if !self.0.contains_key(block) {
+            let mut boxes: Vec<_> = block_models
+                .element_boxes(block)
+                .iter()
+                .map(|element| {
+                    let (min, max) = element.bounds();
+                    (min / 16.0, max / 16.0)
+                })
+                .collect();
+            // Blocks drawn by block entity renderers don't have elements
+            if boxes.is_empty() {
+                boxes.push((Vec3::ZERO, Vec3::ONE));
+            }
+            self.0.insert(block.to_string(), boxes);
+        }
+        &self.0[block]
+    }
+}
+
+/// Intersects a ray with an axis aligned box, returning the distances it enters and leaves at
+fn ray_box(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<(f32, f32)> {
+    let inv = dir.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let enter = t1.min(t2).max_element();
+    let exit = t1.max(t2).min_element();
+    (enter <= exit && exit >= 0.0).then_some((enter.max(0.0), exit))
+}
+
+/// Normal of the face of an axis aligned box that a ray enters it through
+fn entered_face(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> IVec3 {
+    let planes = Vec3::select(dir.cmpgt(Vec3::ZERO), min, max);
+    let t = Vec3::select(
+        dir.cmpeq(Vec3::ZERO),
+        Vec3::NEG_INFINITY,
+        (planes - origin) / dir,
+    );
+    let axis = if t.x >= t.y && t.x >= t.z {
+        IVec3::X
+    } else if t.y >= t.z {
+        IVec3::Y
+    } else {
+        IVec3::Z
+    };
+    -axis * dir.signum().as_ivec3()
+}
+
+/// Walks the blocks a ray passes through in schematic space, front to back, and returns the
+/// first one whose shape it hits along with the face it hit. Blocks cut away by the section or
+/// hidden by the x-ray are passed through.
+fn raycast(
+    block_world: &BlockWorld,
+    block_models: &BlockModels,
+    shapes: &mut BlockShapes,
+    clip: &SectionClip,
+    xray: &Xray,
+    origin: Vec3,
+    dir: Vec3,
+) -> Option<(IVec3, IVec3)> {
+    let (sx, sy, sz) = block_world.blocks.size();
+    let size = Vec3::new(sx as f32, sy as f32, sz as f32);
+    let (enter, exit) = ray_box(origin, dir, Vec3::ZERO, size)?;
+    let exit = exit.min(MAX_DISTANCE);
+
+    // Start just inside the volume so the first cell is the one the ray enters
+    let start = origin + dir * enter;
+    let mut cell = start
+        .floor()
+        .as_ivec3()
+        .clamp(IVec3::ZERO, size.as_ivec3() - 1);
+    let step = dir.signum().as_ivec3();
+    let next_boundary = (cell.as_vec3() + step.max(IVec3::ZERO).as_vec3() - origin) / dir;
+    let mut t_max = Vec3::select(dir.cmpeq(Vec3::ZERO), Vec3::INFINITY, next_boundary);
+    let t_delta = (1.0 / dir).abs();
+
+    loop {
+        let (x, y, z) = (cell.x as usize, cell.y as usize, cell.z as usize);
+        let block = block_world.blocks.get_block_at(x, y, z);
+        let hidden = xray.action(block) == Some(XrayAction::Hide);
+        if block != "minecraft:air" && clip.contains(cell) && !hidden {
+            let offset = cell.as_vec3();
+            let hit = shapes
+                .get(block_models, block)
+                .iter()
+                .filter_map(|&(min, max)| {
+                    let (min, max) = (offset + min, offset + max);
+                    let (enter, _) = ray_box(origin, dir, min, max)?;
+                    Some((enter, entered_face(origin, dir, min, max)))
+                })
+                .min_by(|(a, _), (b, _)| a.total_cmp(b));
+            if let Some((_, face)) = hit {
+                return Some((cell, face));
+            }
+        }
+
+        let t = t_max.min_element();
+        if t > exit {
+            return None;
+        }
+        if t_max.x == t {
+            cell.x += step.x;
+            t_max.x += t_delta.x;
+        } else if t_max.y == t {
+            cell.y += step.y;
+            t_max.y += t_delta.y;
+        } else {
+            cell.z += step.z;
+            t_max.z += t_delta.z;
+        }
+        if cell.cmplt(IVec3::ZERO).any() || cell.cmpge(size.as_ivec3()).any() {
+            return None;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_targeted_block(
+    mut contexts: EguiContexts,
+    mut targeted: ResMut<TargetedBlock>,
+    mut targeted_face: ResMut<TargetedFace>,
+    mut shapes: Local<BlockShapes>,
+    block_world: Res<BlockWorld>,
+    block_models: Res<BlockModels>,
+    section: Res<SectionView>,
+    xray: Res<Xray>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform, &FlyCamera), With<McCamera>>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform, fly_camera)), Ok(root)) =
+        (window.get_single(), camera.get_single(), root.get_single())
+    else {
+        return;
+    };
+
+    let viewport_position = if fly_camera.enabled {
+        Some(Vec2::new(window.width(), window.height()) / 2.0)
+    } else if contexts.ctx_mut().is_pointer_over_area() {
+        // The cursor is over a panel rather than the world
+        None
+    } else {
+        window.cursor_position()
+    };
+    let ray =
+        viewport_position.and_then(|position| camera.viewport_to_world(camera_transform, position));
+    let target = ray.and_then(|ray| {
+        let to_schematic = root.compute_matrix().inverse();
+        let origin = to_schematic.transform_point3(ray.origin);
+        let dir = to_schematic.transform_vector3(*ray.direction);
+        let clip = section.clip();
+        raycast(
+            &block_world,
+            &block_models,
+            &mut shapes,
+            &clip,
+            &xray,
+            origin,
+            dir,
+        )
+    });
+
+    // Only write back on change to avoid triggering change detection every frame
+    let (target, face) = (target.map(|(pos, _)| pos), target.map(|(_, face)| face));
+    if targeted.0 != target {
+        targeted.0 = target;
+    }
+    if targeted_face.0 != face {
+        targeted_face.0 = face;
+    }
+}
+
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TargetedBlock>()
+            .init_resource::<TargetedFace>()
+            .add_systems(
+                Update,
+                update_targeted_block.run_if(in_state(AppLoadState::Finished)),
+            );
+    }
+}
@@ -0,0 +1,214 @@
+//! Color coding of blocks by the value of one block state property, like `facing` or `delay`,
+//! with a legend of the values. Blocks without the property are dimmed, so a wrongly oriented
+//! repeater stands out among thousands of others.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::block::{
+    parse_props, redstone_power_color, split_block_state, Block, BlockLook, BlockPalette,
+};
+use crate::{AppLoadState, BlockWorld};
+
+/// Directions in the order they're listed, with a color that's easy to tell apart for each
+const DIRECTIONS: [(&str, Color); 6] = [
+    ("north", Color::srgb(0.2, 0.4, 1.0)),
+    ("east", Color::srgb(1.0, 0.2, 0.2)),
+    ("south", Color::srgb(1.0, 0.85, 0.1)),
+    ("west", Color::srgb(0.2, 0.85, 0.3)),
+    ("up", Color::srgb(0.95, 0.95, 0.95)),
+    ("down", Color::srgb(0.8, 0.3, 0.9)),
+];
+
+/// The property blocks are colored by, if any
+#[derive(Resource, Default)]
+pub struct PropertyView {
+    pub property: Option<String>,
+}
+
+/// Color for a value of a property, where `idx` is its position among the `count` values
+fn value_color(property: &str, value: &str, idx: usize, count: usize) -> Color {
+    // Signal strength looks the way it does on redstone dust
+    if let ("power", Ok(power)) = (property, value.parse()) {
+        return redstone_power_color(power);
+    }
+    if let Some((_, color)) = DIRECTIONS.iter().find(|(direction, _)| *direction == value) {
+        return *color;
+    }
+    match value {
+        "true" => Color::srgb(0.2, 0.9, 0.2),
+        "false" => Color::srgb(0.9, 0.15, 0.15),
+        _ => Color::hsl(360.0 * idx as f32 / count as f32, 0.85, 0.55),
+    }
+}
+
+/// Values of a property found in the palette, in a natural order with their colors
+fn legend(palette: &BlockPalette, property: &str) -> Vec<(String, Color)> {
+    let mut values: Vec<_> = palette
+        .blocks()
+        .iter()
+        .filter_map(|block| {
+            let props = parse_props(split_block_state(block).1);
+            props.get(property).map(|value| value.to_string())
+        })
+        .collect();
+    values.sort_by_key(|value| {
+        let direction = DIRECTIONS
+            .iter()
+            .position(|(direction, _)| *direction == value.as_str());
+        (direction, value.parse::<i32>().ok(), value.clone())
+    });
+    values.dedup();
+
+    let count = values.len();
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let color = value_color(property, &value, idx, count);
+            (value, color)
+        })
+        .collect()
+}
+
+fn to_egui(color: Color) -> egui::Color32 {
+    let [r, g, b, _] = color.to_srgba().to_u8_array();
+    egui::Color32::from_rgb(r, g, b)
+}
+
+fn property_window(
+    mut contexts: EguiContexts,
+    mut counts: Local<Vec<usize>>,
+    mut view: ResMut<PropertyView>,
+    palette: Res<BlockPalette>,
+    block_world: Res<BlockWorld>,
+) {
+    // Blocks are only counted again once the schematic has been edited
+    if counts.is_empty() || block_world.is_changed() {
+        counts.clear();
+        counts.resize(palette.blocks().len(), 0);
+        let (sx, sy, sz) = block_world.blocks.size();
+        for x in 0..sx {
+            for y in 0..sy {
+                for z in 0..sz {
+                    if let Some(idx) = palette.get(block_world.blocks.get_block_at(x, y, z)) {
+                        counts[idx] += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut properties: Vec<_> = palette
+        .blocks()
+        .iter()
+        .flat_map(|block| parse_props(split_block_state(block).1).into_keys())
+        .collect();
+    properties.sort();
+    properties.dedup();
+
+    let mut property = view.property.clone();
+    egui::Window::new("Color by Property")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ComboBox::from_id_salt("color_by_property")
+                .selected_text(property.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut property, None, "None");
+                    for name in properties {
+                        ui.selectable_value(&mut property, Some(name.to_string()), name);
+                    }
+                });
+
+            let Some(property) = &property else {
+                return;
+            };
+            let mut value_counts: HashMap<&str, usize> = HashMap::new();
+            for (block, count) in palette.blocks().iter().zip(counts.iter()) {
+                if let Some(value) = parse_props(split_block_state(block).1).get(property.as_str())
+                {
+                    *value_counts.entry(*value).or_default() += count;
+                }
+            }
+            egui::Grid::new("property_legend")
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for (value, color) in legend(&palette, property) {
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, to_egui(color));
+                        ui.monospace(&value);
+                        let count = value_counts
+                            .get(value.as_str())
+                            .copied()
+                            .unwrap_or_default();
+                        ui.label(format!("{} blocks", count));
+                        ui.end_row();
+                    }
+                });
+            ui.label("Blocks without the property are dimmed");
+        });
+
+    if property != view.property {
+        view.property = property;
+    }
+}
+
+fn apply_property_view(
+    view: Res<PropertyView>,
+    block_world: Res<BlockWorld>,
+    palette: Res<BlockPalette>,
+    mut blocks: Query<(&Block, &mut BlockLook)>,
+    changed: Query<(), Changed<Block>>,
+) {
+    if !view.is_changed() && changed.is_empty() {
+        return;
+    }
+    let colors: HashMap<_, _> = view
+        .property
+        .as_deref()
+        .map(|property| legend(&palette, property))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    // Tint and dimming of every palette entry
+    let looks: Vec<_> = palette
+        .blocks()
+        .iter()
+        .map(|block| {
+            let Some(property) = &view.property else {
+                return (None, false);
+            };
+            let (name, props) = split_block_state(block);
+            match parse_props(props).get(property.as_str()) {
+                Some(value) => (colors.get(*value).copied(), false),
+                None => (None, name != "minecraft:air"),
+            }
+        })
+        .collect();
+
+    for &entity in block_world.entities.values() {
+        let Ok((block, mut look)) = blocks.get_mut(entity) else {
+            continue;
+        };
+        let (tint, dimmed) = looks[block.index()];
+        if look.tint != tint || look.dimmed != dimmed {
+            look.tint = tint;
+            look.dimmed = dimmed;
+        }
+    }
+}
+
+pub struct PropertyViewPlugin;
+
+impl Plugin for PropertyViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PropertyView>().add_systems(
+            Update,
+            (property_window, apply_property_view)
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
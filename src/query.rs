@@ -0,0 +1,263 @@
+//! Query language for finding block states, like `minecraft:repeater[delay=4]`,
+//! `*[powered=true]` or `#minecraft:slabs`. Queries run over the blocks of the schematic, both
+//! in the search window and headlessly from the command line.
+
+use std::collections::HashSet;
+use std::fs;
+use std::iter;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use mc_schems::Blocks;
+use serde::Deserialize;
+
+use crate::block::{parse_props, split_block_state};
+
+/// Matches text against a pattern where `*` stands for any text
+pub fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Block tag file in a data pack
+#[derive(Deserialize)]
+struct TagFile {
+    #[serde(default)]
+    replace: bool,
+    values: Vec<TagValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagValue {
+    Id(String),
+    Entry {
+        id: String,
+        #[serde(default = "default_required")]
+        required: bool,
+    },
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// Block tags from the game's data and from data packs, which are unpacked folders
+#[derive(Resource)]
+pub struct BlockTags {
+    packs: Vec<PathBuf>,
+}
+
+impl BlockTags {
+    pub fn new(datapacks: Vec<PathBuf>) -> Self {
+        // The game's own data comes first so data packs can add to its tags or replace them
+        Self {
+            packs: iter::once(PathBuf::from(".")).chain(datapacks).collect(),
+        }
+    }
+
+    /// Names of all blocks in a tag like `minecraft:slabs`, including those of nested tags
+    pub fn resolve(&self, tag: &str) -> Result<HashSet<String>> {
+        let mut blocks = HashSet::new();
+        self.resolve_into(tag, &mut blocks, &mut HashSet::new())?;
+        Ok(blocks)
+    }
+
+    fn resolve_into(
+        &self,
+        tag: &str,
+        blocks: &mut HashSet<String>,
+        visited: &mut HashSet<String>,
+    ) -> Result<()> {
+        let (namespace, path) = tag.split_once(':').unwrap_or(("minecraft", tag));
+        if !visited.insert(format!("{}:{}", namespace, path)) {
+            return Ok(());
+        }
+
+        let mut found = false;
+        let mut values = Vec::new();
+        for pack in &self.packs {
+            // The folder was renamed from `blocks` to `block` in 1.21
+            for folder in ["blocks", "block"] {
+                let file = pack
+                    .join("data")
+                    .join(namespace)
+                    .join("tags")
+                    .join(folder)
+                    .join(format!("{}.json", path));
+                let Ok(json) = fs::read_to_string(&file) else {
+                    continue;
+                };
+                let tag_file: TagFile = serde_json::from_str(&json)
+                    .map_err(|err| eyre!("Could not read {}: {}", file.display(), err))?;
+                if tag_file.replace {
+                    values.clear();
+                }
+                values.extend(tag_file.values);
+                found = true;
+            }
+        }
+        if !found {
+            bail!("Unknown block tag #{}:{}", namespace, path);
+        }
+
+        for value in values {
+            let (id, required) = match value {
+                TagValue::Id(id) => (id, true),
+                TagValue::Entry { id, required } => (id, required),
+            };
+            match id.strip_prefix('#') {
+                Some(nested) => match self.resolve_into(nested, blocks, visited) {
+                    Err(_) if !required => {}
+                    result => result?,
+                },
+                None if id.contains(':') => {
+                    blocks.insert(id);
+                }
+                None => {
+                    blocks.insert(format!("minecraft:{}", id));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+enum NamePattern {
+    /// Name with wildcards, where the `minecraft:` namespace is optional
+    Wildcard(String),
+    /// Full names of the blocks in a tag
+    Tag(HashSet<String>),
+}
+
+/// One alternative of a query
+struct QueryTerm {
+    name: NamePattern,
+    /// Properties the block must have, where values may contain wildcards
+    props: Vec<(String, String)>,
+}
+
+impl QueryTerm {
+    fn parse(term: &str, tags: &BlockTags) -> Result<Self> {
+        let (name, props) = match term.split_once('[') {
+            Some((name, props)) => {
+                let Some(props) = props.strip_suffix(']') else {
+                    bail!("Missing ] in {}", term);
+                };
+                (name, props)
+            }
+            None => (term, ""),
+        };
+
+        let name = match name.strip_prefix('#') {
+            Some(tag) => NamePattern::Tag(tags.resolve(tag)?),
+            None if name.is_empty() => NamePattern::Wildcard("*".to_string()),
+            None => NamePattern::Wildcard(name.to_string()),
+        };
+        let props = props
+            .split(',')
+            .filter(|prop| !prop.trim().is_empty())
+            .map(|prop| match prop.split_once('=') {
+                Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+                None => Err(eyre!("Expected a value for property {} in {}", prop, term)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { name, props })
+    }
+
+    fn matches(&self, block: &str) -> bool {
+        let (name, props) = split_block_state(block);
+        let name_matches = match &self.name {
+            NamePattern::Wildcard(pattern) if pattern.contains(':') => {
+                wildcard_matches(pattern, name)
+            }
+            NamePattern::Wildcard(pattern) => {
+                wildcard_matches(pattern, name.trim_start_matches("minecraft:"))
+            }
+            NamePattern::Tag(blocks) => blocks.contains(name),
+        };
+        if !name_matches {
+            return false;
+        }
+        let props = parse_props(props);
+        self.props.iter().all(|(key, pattern)| {
+            props
+                .get(key.as_str())
+                .is_some_and(|value| wildcard_matches(pattern, value))
+        })
+    }
+}
+
+/// A parsed query, whose alternatives are separated by whitespace
+pub struct BlockQuery {
+    terms: Vec<QueryTerm>,
+}
+
+impl BlockQuery {
+    pub fn parse(query: &str, tags: &BlockTags) -> Result<Self> {
+        let terms = query
+            .split_whitespace()
+            .map(|term| QueryTerm::parse(term, tags))
+            .collect::<Result<Vec<_>>>()?;
+        if terms.is_empty() {
+            bail!("The query is empty");
+        }
+        Ok(Self { terms })
+    }
+
+    /// Whether a block state like `minecraft:repeater[delay=4,facing=north]` matches
+    pub fn matches(&self, block: &str) -> bool {
+        self.terms.iter().any(|term| term.matches(block))
+    }
+
+    /// Positions of the matching blocks layer by layer from the bottom. Air is never matched.
+    pub fn find(&self, blocks: &Blocks) -> Vec<IVec3> {
+        // Schematics only use a few block states, so each one is only tested once
+        let mut tested: HashMap<&str, bool> = HashMap::new();
+        let mut found = Vec::new();
+        let (sx, sy, sz) = blocks.size();
+        for y in 0..sy {
+            for z in 0..sz {
+                for x in 0..sx {
+                    let block = blocks.get_block_at(x, y, z);
+                    let matches = *tested
+                        .entry(block)
+                        .or_insert_with(|| block != "minecraft:air" && self.matches(block));
+                    if matches {
+                        found.push(IVec3::new(x as i32, y as i32, z as i32));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Prints the blocks matching a query with their positions shifted by `offset`, for use
+/// without a window
+pub fn print_matches(query: &str, tags: &BlockTags, blocks: &Blocks, offset: IVec3) -> Result<()> {
+    let found = BlockQuery::parse(query, tags)?.find(blocks);
+    for pos in &found {
+        let block = blocks.get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+        let shown = *pos + offset;
+        println!("{} {} {} {}", shown.x, shown.y, shown.z, block);
+    }
+    println!("{} matches", found.len());
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! Find and replace for blocks in the whole schematic or the selection, like replacing
+//! `minecraft:stone_slab` with `minecraft:smooth_stone_slab` while keeping `type` and
+//! `waterlogged`. Matches and replacements are worked out once per entry of the block palette.
+
+use std::collections::HashSet;
+use std::fs;
+use std::mem;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+
+use crate::block::{parse_props, split_block_state, Block, BlockModels, BlockPalette};
+use crate::editor::{EditBlocks, StateChange};
+use crate::lang::Lang;
+use crate::query::{BlockQuery, BlockTags};
+use crate::resources::asset_pack::{load_asset_pack, resource_path};
+use crate::selection::Selection;
+use crate::{AppLoadState, BlockWorld};
+
+/// The block state matching blocks are replaced with
+struct Replacement {
+    name: String,
+    /// Properties given with the replacement, which win over kept ones
+    props: Vec<(String, String)>,
+    /// Properties of the replaced block to keep, or all of them for `None`
+    keep: Option<Vec<String>>,
+    /// Properties the block is known to have, which are the ones kept by `*`
+    defined: HashSet<String>,
+}
+
+/// Properties of a block that its blockstates file picks models by, along with the ones it's
+/// seen with in the palette. Properties that don't change the model, like `waterlogged`, are
+/// only known from the palette, but they're still kept when they're named in the properties to
+/// keep.
+fn block_properties(name: &str, palette: &BlockPalette) -> Result<HashSet<String>> {
+    let path = resource_path("blockstates", &format!("{}.json", name));
+    let blockstates: serde_json::Value = fs::read(&path)
+        .map_err(|err| eyre!("Unknown block {}: {}", name, err))
+        .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))?;
+
+    let mut defined = HashSet::new();
+    if let Some(variants) = blockstates["variants"].as_object() {
+        for variant in variants.keys() {
+            defined.extend(parse_props(variant).into_keys().map(String::from));
+        }
+    }
+    // Conditions of multipart blocks may be nested in `OR` and `AND` lists
+    let mut conditions: Vec<_> = blockstates["multipart"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|case| &case["when"])
+        .collect();
+    while let Some(condition) = conditions.pop() {
+        for (key, value) in condition.as_object().into_iter().flatten() {
+            match value.as_array() {
+                Some(nested) => conditions.extend(nested),
+                None => {
+                    defined.insert(key.clone());
+                }
+            }
+        }
+    }
+    for block in palette.blocks() {
+        let (block_name, props) = split_block_state(block);
+        if block_name == name {
+            defined.extend(parse_props(props).into_keys().map(String::from));
+        }
+    }
+    Ok(defined)
+}
+
+impl Replacement {
+    /// Parses a block like `smooth_stone_slab[type=double]` and the properties to keep, which
+    /// are listed like `type,waterlogged` or `*` for all of them
+    fn parse(block: &str, keep: &str, palette: &BlockPalette) -> Result<Self> {
+        let block = block.trim();
+        if block.is_empty() {
+            bail!("The replacement is empty");
+        }
+        let (name, props) = split_block_state(block);
+        let name = if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("minecraft:{}", name)
+        };
+        let mut explicit = Vec::new();
+        for prop in props.split(',').filter(|prop| !prop.trim().is_empty()) {
+            let Some((key, value)) = prop.split_once('=') else {
+                bail!("Expected a value for property {} in {}", prop, block);
+            };
+            explicit.push((key.trim().to_string(), value.trim().to_string()));
+        }
+        let keep = match keep.trim() {
+            "*" => None,
+            keep => Some(
+                keep.split(',')
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty())
+                    .collect(),
+            ),
+        };
+        Ok(Self {
+            defined: block_properties(&name, palette)?,
+            name,
+            props: explicit,
+            keep,
+        })
+    }
+
+    /// The state a block is replaced with, with the properties sorted by name like in the states
+    /// of schematics
+    fn state_for(&self, block: &str) -> String {
+        let (_, props) = split_block_state(block);
+        let kept = parse_props(props).into_iter().filter(|(key, _)| {
+            let given = self.props.iter().any(|(given, _)| given == key);
+            // Properties that are named are kept even if the block isn't known to have them
+            let kept = match &self.keep {
+                Some(keep) => keep.iter().any(|kept| kept == key),
+                None => self.defined.contains(*key),
+            };
+            kept && !given
+        });
+        let given = self
+            .props
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()));
+        let mut props: Vec<_> = kept.chain(given).collect();
+        props.sort();
+        let props: Vec<_> = props
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        if props.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}[{}]", self.name, props.join(","))
+        }
+    }
+}
+
+/// Palette entries that would be replaced, with their replacement and how many blocks use them
+struct Preview {
+    states: Vec<(usize, String, usize)>,
+    /// Whether only blocks in the selection are replaced
+    in_selection: bool,
+}
+
+/// Works out which palette entries match and what they're replaced with, then counts their
+/// blocks. Blocks with states that don't match are never looked at again.
+fn preview(
+    query: &BlockQuery,
+    replacement: &Replacement,
+    palette: &BlockPalette,
+    blocks: &Query<&Block>,
+    block_world: &BlockWorld,
+    region: Option<(IVec3, IVec3)>,
+) -> Vec<(usize, String, usize)> {
+    let replaced: Vec<_> = palette
+        .blocks()
+        .iter()
+        .map(|block| {
+            let state = replacement.state_for(block);
+            (block != "minecraft:air" && query.matches(block) && state != *block).then_some(state)
+        })
+        .collect();
+    let mut counts = vec![0; replaced.len()];
+    for block in blocks.iter_many(block_world.entities.values()) {
+        let inside = region.map_or(true, |(min, size)| {
+            block.pos().cmpge(min).all() && block.pos().cmplt(min + size).all()
+        });
+        if inside && replaced[block.index()].is_some() {
+            counts[block.index()] += 1;
+        }
+    }
+    replaced
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, state)| Some((idx, state?, counts[idx])))
+        .filter(|(_, _, count)| *count > 0)
+        .collect()
+}
+
+struct ReplaceWindow {
+    find: String,
+    replace: String,
+    keep: String,
+    in_selection: bool,
+    preview: Option<Preview>,
+    error: Option<String>,
+}
+
+impl Default for ReplaceWindow {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            keep: "*".to_string(),
+            in_selection: false,
+            preview: None,
+            error: None,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn replace_window(
+    mut contexts: EguiContexts,
+    mut window: Local<ReplaceWindow>,
+    mut edits: EventWriter<EditBlocks>,
+    blocks: Query<&Block>,
+    palette: Res<BlockPalette>,
+    mut block_models: ResMut<BlockModels>,
+    block_world: Res<BlockWorld>,
+    selection: Res<Selection>,
+    tags: Res<BlockTags>,
+    lang: Res<Lang>,
+) {
+    let window = &mut *window;
+    // Edits from elsewhere change the counts
+    if block_world.is_changed() {
+        window.preview = None;
+    }
+    let mut replace_sent = false;
+    egui::Window::new("Find & Replace")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let mut changed = false;
+            egui::Grid::new("replace_fields")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Find");
+                    let find = egui::TextEdit::singleline(&mut window.find)
+                        .hint_text("minecraft:stone_slab");
+                    changed |= ui.add(find).changed();
+                    ui.end_row();
+                    ui.label("Replace with");
+                    let replace = egui::TextEdit::singleline(&mut window.replace)
+                        .hint_text("minecraft:smooth_stone_slab");
+                    changed |= ui.add(replace).changed();
+                    ui.end_row();
+                    ui.label("Keep properties");
+                    let keep = egui::TextEdit::singleline(&mut window.keep)
+                        .hint_text("* or type,waterlogged");
+                    changed |= ui.add(keep).changed();
+                    ui.end_row();
+                });
+            let region = selection.region();
+            let in_selection =
+                egui::Checkbox::new(&mut window.in_selection, "Only in the selection");
+            changed |= ui.add_enabled(region.is_some(), in_selection).changed();
+            if changed {
+                window.preview = None;
+            }
+
+            if ui.button("Preview").clicked() {
+                let parsed = BlockQuery::parse(&window.find, &tags).and_then(|query| {
+                    let replacement = Replacement::parse(&window.replace, &window.keep, &palette)?;
+                    Ok((query, replacement))
+                });
+                let previewed = parsed.and_then(|(query, replacement)| {
+                    let in_selection = window.in_selection && region.is_some();
+                    let region = region.filter(|_| in_selection);
+                    let states = preview(
+                        &query,
+                        &replacement,
+                        &palette,
+                        &blocks,
+                        &block_world,
+                        region,
+                    );
+                    // Replacing with states that can't be rendered would leave holes
+                    let asset_pack = load_asset_pack()?;
+                    for (_, state, _) in &states {
+                        block_models.insert(&asset_pack, state).map_err(|err| {
+                            eyre!("Could not load the model of {}: {}", state, err)
+                        })?;
+                    }
+                    Ok(Preview {
+                        states,
+                        in_selection,
+                    })
+                });
+                match previewed {
+                    Ok(preview) => {
+                        window.preview = Some(preview);
+                        window.error = None;
+                    }
+                    Err(err) => {
+                        window.preview = None;
+                        window.error = Some(err.to_string());
+                    }
+                }
+            }
+            if let Some(error) = &window.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            let Some(preview) = &window.preview else {
+                return;
+            };
+
+            let total: usize = preview.states.iter().map(|(_, _, count)| count).sum();
+            ui.label(format!("{} blocks will be replaced", total));
+            egui::ScrollArea::vertical()
+                .max_height(240.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("replace_preview")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (idx, state, count) in &preview.states {
+                                let from = &palette.blocks()[*idx];
+                                let text = format!(
+                                    "{} → {}",
+                                    lang.block_name(from),
+                                    lang.block_name(state)
+                                );
+                                ui.label(text)
+                                    .on_hover_text(format!("{}\n→ {}", from, state));
+                                ui.label(count.to_string());
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            if !ui
+                .add_enabled(total > 0, egui::Button::new("Replace"))
+                .clicked()
+            {
+                return;
+            }
+            // Blocks are changed per palette entry, which keeps their entities
+            let mut positions = vec![Vec::new(); palette.blocks().len()];
+            let mut replaced = vec![false; palette.blocks().len()];
+            for (idx, _, _) in &preview.states {
+                replaced[*idx] = true;
+            }
+            let region = region.filter(|_| preview.in_selection);
+            for block in blocks.iter_many(block_world.entities.values()) {
+                let inside = region.map_or(true, |(min, size)| {
+                    block.pos().cmpge(min).all() && block.pos().cmplt(min + size).all()
+                });
+                if inside && replaced[block.index()] {
+                    positions[block.index()].push(block.pos());
+                }
+            }
+            let states = preview
+                .states
+                .iter()
+                .map(|(idx, state, _)| StateChange {
+                    from: palette.blocks()[*idx].clone(),
+                    to: state.clone(),
+                    positions: mem::take(&mut positions[*idx]),
+                    block_entities: Vec::new(),
+                })
+                .collect();
+            edits.send(EditBlocks {
+                states,
+                ..EditBlocks::new(Vec::new())
+            });
+            replace_sent = true;
+        });
+    if replace_sent {
+        window.preview = None;
+    }
+}
+
+pub struct ReplacePlugin;
+
+impl Plugin for ReplacePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            replace_window.run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
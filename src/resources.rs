@@ -1,4 +1,5 @@
 pub mod asset_pack;
+pub mod font;
 pub mod mc_meta;
 pub mod textures;
 
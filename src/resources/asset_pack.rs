@@ -1,6 +1,15 @@
+use std::path::{Path, PathBuf};
+
 use color_eyre::Result;
 use minecraft_assets::api::AssetPack;
 
 pub fn load_asset_pack() -> Result<AssetPack> {
     Ok(AssetPack::at_path("."))
 }
+
+/// Path of a file in the asset pack, given a resource location like `minecraft:font/ascii.png`
+/// and the folder it's relative to, like `textures`. The folder may be empty.
+pub fn resource_path(folder: &str, location: &str) -> PathBuf {
+    let (namespace, path) = location.split_once(':').unwrap_or(("minecraft", location));
+    Path::new("assets").join(namespace).join(folder).join(path)
+}
@@ -0,0 +1,313 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::utils::HashMap;
+use color_eyre::Result;
+use serde::Deserialize;
+
+use super::asset_pack::resource_path;
+use super::textures::CpuImage;
+
+/// Texels per font pixel when rasterizing text, enough to fit the half size unicode glyphs
+pub const TEXT_RESOLUTION: u32 = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ProviderDefinition {
+    Bitmap {
+        file: String,
+        #[serde(default = "default_bitmap_height")]
+        height: f32,
+        ascent: f32,
+        chars: Vec<String>,
+    },
+    Space {
+        advances: HashMap<String, f32>,
+    },
+    Reference {
+        id: String,
+    },
+    LegacyUnicode {
+        sizes: String,
+        template: String,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+fn default_bitmap_height() -> f32 {
+    8.0
+}
+
+#[derive(Debug, Deserialize)]
+struct FontDefinition {
+    providers: Vec<ProviderDefinition>,
+}
+
+/// Cuts out the mask of a glyph from a grid cell
+fn glyph_mask(image: &CpuImage, x: u32, y: u32, width: u32, height: u32) -> Vec<bool> {
+    let mut mask = Vec::with_capacity((width * height) as usize);
+    for gy in y..y + height {
+        for gx in x..x + width {
+            mask.push(image.is_opaque(gx, gy));
+        }
+    }
+    mask
+}
+
+struct Glyph {
+    /// Which pixels of the glyph are set, row by row
+    mask: Vec<bool>,
+    width: u32,
+    /// Font pixels per glyph pixel
+    scale: f32,
+    /// Offset from the top of the line to the top of the glyph, in font pixels
+    top: f32,
+    /// Distance to the next glyph, in font pixels
+    advance: f32,
+}
+
+struct LegacyUnicode {
+    /// Start and end column of every glyph in the basic multilingual plane
+    sizes: Vec<u8>,
+    /// Location of the page textures, with `%s` standing in for the page number
+    template: String,
+    pages: HashMap<u32, Option<CpuImage>>,
+}
+
+/// The default Minecraft font, loaded from `font/default.json`
+#[derive(Resource)]
+pub struct McFont {
+    glyphs: HashMap<char, Glyph>,
+    /// Fallback for characters not covered by any bitmap, loaded lazily page by page
+    legacy_unicode: Option<LegacyUnicode>,
+}
+
+impl McFont {
+    pub fn load() -> Result<Self> {
+        let mut font = McFont {
+            glyphs: HashMap::new(),
+            legacy_unicode: None,
+        };
+        font.load_definition("minecraft:default")?;
+        Ok(font)
+    }
+
+    fn load_definition(&mut self, id: &str) -> Result<()> {
+        let path = resource_path("font", &format!("{}.json", id));
+        let definition: FontDefinition = serde_json::from_slice(&fs::read(path)?)?;
+
+        // Earlier providers take priority, so glyphs that already exist are never replaced
+        for provider in definition.providers {
+            match provider {
+                ProviderDefinition::Bitmap {
+                    file,
+                    height,
+                    ascent,
+                    chars,
+                } => self.load_bitmap(&file, height, ascent, &chars)?,
+                ProviderDefinition::Space { advances } => {
+                    for (c, advance) in advances {
+                        let Some(c) = c.chars().next() else {
+                            continue;
+                        };
+                        self.glyphs.entry(c).or_insert(Glyph {
+                            mask: Vec::new(),
+                            width: 0,
+                            scale: 1.0,
+                            top: 0.0,
+                            advance,
+                        });
+                    }
+                }
+                ProviderDefinition::Reference { id } => self.load_definition(&id)?,
+                ProviderDefinition::LegacyUnicode { sizes, template } => {
+                    if self.legacy_unicode.is_none() {
+                        self.legacy_unicode = Some(LegacyUnicode {
+                            // Unlike the pages, the sizes aren't relative to the textures folder
+                            sizes: fs::read(resource_path("", &sizes))?,
+                            template,
+                            pages: HashMap::new(),
+                        });
+                    }
+                }
+                ProviderDefinition::Unsupported => {
+                    warn!("Skipping unsupported font provider in {}", id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load_bitmap(&mut self, file: &str, height: f32, ascent: f32, rows: &[String]) -> Result<()> {
+        let image = CpuImage::load(file)?;
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or_default() as u32;
+        if columns == 0 {
+            return Ok(());
+        }
+        let cell_width = image.width / columns;
+        let cell_height = image.height / rows.len() as u32;
+        let scale = height / cell_height as f32;
+
+        for (row, chars) in rows.iter().enumerate() {
+            for (column, &c) in chars.iter().enumerate() {
+                if c == '\0' || self.glyphs.contains_key(&c) {
+                    continue;
+                }
+                let x = column as u32 * cell_width;
+                let y = row as u32 * cell_height;
+                // Glyphs are as wide as their rightmost set column
+                let width = (0..cell_width)
+                    .rev()
+                    .find(|&gx| (0..cell_height).any(|gy| image.is_opaque(x + gx, y + gy)))
+                    .map_or(0, |gx| gx + 1);
+                // Packs without a space provider rely on the blank space in ascii.png
+                let advance = if c == ' ' && width == 0 {
+                    4.0
+                } else {
+                    (0.5 + width as f32 * scale).floor() + 1.0
+                };
+                self.glyphs.insert(
+                    c,
+                    Glyph {
+                        mask: glyph_mask(&image, x, y, width, cell_height),
+                        width,
+                        scale,
+                        top: 7.0 - ascent,
+                        advance,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn glyph(&mut self, c: char) -> Option<&Glyph> {
+        if !self.glyphs.contains_key(&c) {
+            let glyph = self.load_unicode_glyph(c)?;
+            self.glyphs.insert(c, glyph);
+        }
+        self.glyphs.get(&c)
+    }
+
+    fn load_unicode_glyph(&mut self, c: char) -> Option<Glyph> {
+        let unicode = self.legacy_unicode.as_mut()?;
+        let size = *unicode.sizes.get(c as usize)?;
+        if size == 0 {
+            return None;
+        }
+        let start = (size >> 4) as u32;
+        let end = (size & 0xF) as u32 + 1;
+
+        let page_idx = c as u32 >> 8;
+        let page = unicode.pages.entry(page_idx).or_insert_with(|| {
+            let location = unicode.template.replace("%s", &format!("{:02x}", page_idx));
+            CpuImage::load(&location)
+                .inspect_err(|err| warn!("Could not load unicode page {}: {}", location, err))
+                .ok()
+        });
+        let page = page.as_ref()?;
+
+        // Pages are a 16x16 grid of glyphs, drawn at half size
+        let cell_size = page.width / 16;
+        let x = (c as u32 & 0xF) * cell_size;
+        let y = ((c as u32 >> 4) & 0xF) * cell_size;
+        let width = end.saturating_sub(start);
+        Some(Glyph {
+            mask: glyph_mask(page, x + start, y, width, cell_size),
+            width,
+            scale: 8.0 / cell_size as f32,
+            top: 0.0,
+            advance: width as f32 / 2.0 + 1.0,
+        })
+    }
+
+    /// Width of a line of text in font pixels
+    pub fn width(&mut self, text: impl IntoIterator<Item = char>) -> f32 {
+        text.into_iter()
+            .filter_map(|c| self.glyph(c).map(|glyph| glyph.advance))
+            .sum()
+    }
+
+    /// Draws a line of text with its top left corner at `x` and `y`, in font pixels
+    pub fn draw(
+        &mut self,
+        canvas: &mut TextCanvas,
+        text: impl IntoIterator<Item = (char, Color)>,
+        mut x: f32,
+        y: f32,
+    ) {
+        for (c, color) in text {
+            let Some(glyph) = self.glyph(c) else {
+                continue;
+            };
+            for (idx, _) in glyph.mask.iter().enumerate().filter(|(_, set)| **set) {
+                let gx = (idx as u32 % glyph.width) as f32;
+                let gy = (idx as u32 / glyph.width) as f32;
+                canvas.fill(
+                    x + gx * glyph.scale,
+                    y + glyph.top + gy * glyph.scale,
+                    glyph.scale,
+                    color,
+                );
+            }
+            x += glyph.advance;
+        }
+    }
+}
+
+/// An RGBA image that text gets rasterized into
+pub struct TextCanvas {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl TextCanvas {
+    /// Creates a transparent canvas, with the size given in font pixels
+    pub fn new(width: u32, height: u32) -> Self {
+        let width = width * TEXT_RESOLUTION;
+        let height = height * TEXT_RESOLUTION;
+        Self {
+            width,
+            height,
+            data: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// Fills a square of font pixels
+    fn fill(&mut self, x: f32, y: f32, size: f32, color: Color) {
+        let res = TEXT_RESOLUTION as f32;
+        let x0 = (x * res).round().max(0.0) as u32;
+        let y0 = (y * res).round().max(0.0) as u32;
+        let x1 = (((x + size) * res).round() as u32).min(self.width);
+        let y1 = (((y + size) * res).round() as u32).min(self.height);
+        let color = color.to_srgba().to_u8_array();
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let idx = ((py * self.width + px) * 4) as usize;
+                self.data[idx..idx + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    pub fn into_image(self) -> Image {
+        let mut image = Image::new(
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+}
@@ -1,41 +1,100 @@
+use std::fs;
+
 use bevy::asset::LoadedFolder;
 use bevy::prelude::*;
-use bevy::render::texture::ImageSampler;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{CompressedImageFormats, ImageSampler, ImageType};
 use bevy::utils::{HashMap, HashSet};
+use color_eyre::Result;
 use minecraft_assets::schemas::models::{Texture, Textures};
 
+use crate::resources::asset_pack::resource_path;
 use crate::resources::mc_meta::McMetaAsset;
 use crate::AppLoadState;
 
+/// Folders of textures that get packed into the texture atlas
+const TEXTURE_FOLDERS: &[&str] = &[
+    "minecraft/textures/block",
+    "minecraft/textures/entity/armorstand",
+    "minecraft/textures/entity/bed",
+    "minecraft/textures/entity/bell",
+    "minecraft/textures/entity/boat",
+    "minecraft/textures/entity/chest",
+    "minecraft/textures/entity/chest_boat",
+    "minecraft/textures/entity/creeper",
+    "minecraft/textures/entity/piglin",
+    "minecraft/textures/entity/player/wide",
+    "minecraft/textures/entity/shulker",
+    "minecraft/textures/entity/signs",
+    "minecraft/textures/entity/skeleton",
+    "minecraft/textures/entity/zombie",
+    "minecraft/textures/item",
+    "minecraft/textures/painting",
+];
+
+/// Textures that get packed into the atlas on their own, since their folder holds a lot more
+const TEXTURE_FILES: &[&str] = &[
+    "minecraft/textures/entity/enchanting_table_book.png",
+    "minecraft/textures/entity/minecart.png",
+];
+
 #[derive(Resource, Default)]
-pub struct McTexturesFolder(Handle<LoadedFolder>);
+pub struct McTexturesFolder {
+    folders: Vec<Handle<LoadedFolder>>,
+    files: Vec<Handle<Image>>,
+}
 
 /// System to start loading of textures
 pub fn load_textures(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(McTexturesFolder(
-        asset_server.load_folder("minecraft/textures/block"),
-    ));
+    commands.insert_resource(McTexturesFolder {
+        folders: TEXTURE_FOLDERS
+            .iter()
+            .map(|&folder| asset_server.load_folder(folder))
+            .collect(),
+        files: TEXTURE_FILES
+            .iter()
+            .map(|&file| asset_server.load(file))
+            .collect(),
+    });
 }
 
 pub fn check_textures(
     mut commands: Commands,
-    mut events: EventReader<AssetEvent<LoadedFolder>>,
     mut next_state: ResMut<NextState<AppLoadState>>,
     folder_res: Res<McTexturesFolder>,
+    asset_server: Res<AssetServer>,
     loaded_folders: Res<Assets<LoadedFolder>>,
     mut textures: ResMut<Assets<Image>>,
     mut mc_metas: ResMut<Assets<McMetaAsset>>,
 ) {
-    let folder_handle = &folder_res.0;
     // Advance the `AppState` once all sprite handles have been loaded by the `AssetServer`
-    for event in events.read() {
-        if event.is_loaded_with_dependencies(folder_handle) {
-            let folder = loaded_folders.get(folder_handle).unwrap();
-            let atlas = create_texture_atlas(folder, &mut textures, &mut mc_metas);
-            commands.insert_resource(atlas);
-            next_state.set(AppLoadState::Finished);
-        }
+    let all_loaded = folder_res
+        .folders
+        .iter()
+        .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+        && folder_res
+            .files
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle));
+    if !all_loaded {
+        return;
     }
+
+    let handles = folder_res
+        .folders
+        .iter()
+        .flat_map(|handle| loaded_folders.get(handle).unwrap().handles.iter().cloned())
+        .chain(
+            folder_res
+                .files
+                .iter()
+                .map(|handle| handle.clone().untyped()),
+        )
+        .collect();
+    let atlas = create_texture_atlas(handles, &mut textures, &mut mc_metas);
+    commands.insert_resource(atlas);
+    next_state.set(AppLoadState::Finished);
 }
 
 #[derive(Resource)]
@@ -48,18 +107,32 @@ pub struct TextureAtlas {
 
 pub struct TextureDetails {
     pub rect: Rect,
+    /// Size of the original texture in pixels
+    pub size: Vec2,
     pub has_transparency: bool,
 }
 
 impl TextureAtlas {
+    fn texture_path(texture_name: &str) -> String {
+        let texture_name = texture_name.trim_start_matches("minecraft:");
+        if texture_name.contains('/') {
+            format!("minecraft/textures/{}.png", texture_name)
+        } else {
+            format!("minecraft/textures/block/{}.png", texture_name)
+        }
+    }
+
+    pub fn has_texture(&self, texture_name: &str) -> bool {
+        self.mapping.contains_key(&Self::texture_path(texture_name))
+    }
+
+    /// Looks up a texture by its resource location, such as `minecraft:block/stone` or
+    /// `entity/chest/normal`. Names without a folder are assumed to be block textures.
     pub fn get_tex_details(&self, texture_name: &str) -> TextureDetails {
-        let texture_name = texture_name
-            .trim_start_matches("minecraft:")
-            .trim_start_matches("block/");
-        let texture_path = format!("minecraft/textures/block/{}.png", texture_name);
-        let image_id = self.mapping[&texture_path];
+        let image_id = self.mapping[&Self::texture_path(texture_name)];
         let idx_in_atlas = self.layout.get_texture_index(image_id).unwrap();
         let mut atlas_rect = self.layout.textures[idx_in_atlas].as_rect();
+        let size = atlas_rect.size();
 
         // Convert texture pixel coordinates to normalized
         atlas_rect.min /= self.layout.size.as_vec2();
@@ -67,6 +140,7 @@ impl TextureAtlas {
 
         TextureDetails {
             rect: atlas_rect,
+            size,
             has_transparency: self.has_transparency[idx_in_atlas],
         }
     }
@@ -82,14 +156,16 @@ impl TextureDetails {
 }
 
 fn create_texture_atlas(
-    folder: &LoadedFolder,
+    handles: Vec<UntypedHandle>,
     textures: &mut ResMut<Assets<Image>>,
     mc_metas: &mut ResMut<Assets<McMetaAsset>>,
 ) -> TextureAtlas {
     let mut texture_atlas_builder = TextureAtlasBuilder::default();
+    // Entity textures are a lot larger than block textures
+    texture_atlas_builder.max_size(UVec2::splat(4096));
 
     let mut animated_textures = HashSet::new();
-    for handle in folder.handles.iter() {
+    for handle in handles.iter() {
         let Ok(meta_id) = handle.id().try_typed::<McMetaAsset>() else {
             continue;
         };
@@ -100,7 +176,7 @@ fn create_texture_atlas(
 
     let mut mapping = HashMap::new();
     // Build a texture atlas using the individual sprites
-    for handle in folder.handles.iter() {
+    for handle in handles.iter() {
         let id = handle.id().typed_unchecked::<Image>();
         if animated_textures.contains(&id) {
             // We already handled animated textures above
@@ -167,3 +243,62 @@ pub fn resolve_textures_completely(textures: Textures) -> Textures {
 
     resolved_textures.into()
 }
+
+/// An RGBA8 image decoded on the CPU, for textures that get processed before they're used
+pub struct CpuImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl CpuImage {
+    /// Loads a texture from the asset pack, given a resource location like
+    /// `minecraft:font/ascii.png`
+    pub fn load(location: &str) -> Result<Self> {
+        let bytes = fs::read(resource_path("textures", location))?;
+        let image = Image::from_buffer(
+            &bytes,
+            ImageType::Extension("png"),
+            CompressedImageFormats::NONE,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::default(),
+        )?;
+        let rgba = image.try_into_dynamic()?.to_rgba8();
+        Ok(Self {
+            width: rgba.width(),
+            height: rgba.height(),
+            rgba: rgba.into_raw(),
+        })
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * self.width + x) * 4) as usize;
+        [
+            self.rgba[idx],
+            self.rgba[idx + 1],
+            self.rgba[idx + 2],
+            self.rgba[idx + 3],
+        ]
+    }
+
+    pub fn is_opaque(&self, x: u32, y: u32) -> bool {
+        self.pixel(x, y)[3] != 0
+    }
+
+    pub fn into_image(self) -> Image {
+        let mut image = Image::new(
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.rgba,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+}
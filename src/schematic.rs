@@ -0,0 +1,489 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use color_eyre::Result;
+use nbt::{Blob, Value};
+
+use crate::BlockWorld;
+
+/// A block entity stored in the schematic
+#[derive(Debug, Clone)]
+pub struct BlockEntity {
+    pub id: String,
+    /// Compound tag holding the block entity's data
+    pub data: Value,
+}
+
+impl BlockEntity {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match &self.data {
+            Value::Compound(data) => data.get(key),
+            _ => None,
+        }
+    }
+}
+
+/// An entity stored in the schematic, like an item frame or armor stand
+#[derive(Debug, Clone)]
+pub struct SchematicEntity {
+    pub id: String,
+    /// Position relative to the schematic origin, in blocks
+    pub pos: Vec3,
+    /// Compound tag holding the entity's data
+    pub data: Value,
+}
+
+impl SchematicEntity {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match &self.data {
+            Value::Compound(data) => data.get(key),
+            _ => None,
+        }
+    }
+
+    /// Yaw and pitch in degrees, from the `Rotation` tag
+    pub fn rotation(&self) -> (f32, f32) {
+        match self.get("Rotation") {
+            Some(Value::List(rotation)) => {
+                let angle = |idx: usize| match rotation.get(idx) {
+                    Some(Value::Float(angle)) => *angle,
+                    _ => 0.0,
+                };
+                (angle(0), angle(1))
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+/// Biomes saved with the schematic. Version 2 stores one per column and version 3 one per block.
+pub struct SchematicBiomes {
+    palette: Vec<String>,
+    data: Vec<usize>,
+    width: usize,
+    length: usize,
+    columns: bool,
+}
+
+impl SchematicBiomes {
+    fn read(
+        palette: &Value,
+        data: &Value,
+        width: usize,
+        length: usize,
+        columns: bool,
+    ) -> Option<Self> {
+        let (Value::Compound(entries), Value::ByteArray(bytes)) = (palette, data) else {
+            return None;
+        };
+        let mut names = Vec::new();
+        for (name, id) in entries {
+            let Value::Int(id) = id else {
+                continue;
+            };
+            let id = usize::try_from(*id).ok()?;
+            if names.len() <= id {
+                names.resize(id + 1, String::new());
+            }
+            names[id] = name.clone();
+        }
+        Some(Self {
+            palette: names,
+            data: read_varints(bytes),
+            width,
+            length,
+            columns,
+        })
+    }
+
+    /// Palette index of the biome at a position relative to the schematic origin
+    fn index(&self, pos: IVec3) -> Option<usize> {
+        if pos.cmplt(IVec3::ZERO).any() {
+            return None;
+        }
+        let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+        if x >= self.width || z >= self.length {
+            return None;
+        }
+        let mut idx = x + z * self.width;
+        if !self.columns {
+            idx += y * self.width * self.length;
+        }
+        self.data.get(idx).copied()
+    }
+
+    /// The biome at a position relative to the schematic origin
+    pub fn get(&self, pos: IVec3) -> Option<&str> {
+        let name = self.palette.get(self.index(pos)?)?;
+        (!name.is_empty()).then_some(name.as_str())
+    }
+
+    /// The biomes moved to new positions in a schematic of a new size, like when it's rotated.
+    /// `size` is the size of the schematic they were read from.
+    pub fn moved(&self, size: IVec3, new_size: IVec3, new_pos: impl Fn(IVec3) -> IVec3) -> Self {
+        // Palettes may have gaps, which are stored as empty names that read as no biome
+        let mut palette = self.palette.clone();
+        let missing = palette.len();
+        palette.push(String::new());
+        let mut data = vec![missing; (new_size.x * new_size.y * new_size.z).max(0) as usize];
+        for y in 0..size.y {
+            for z in 0..size.z {
+                for x in 0..size.x {
+                    let pos = IVec3::new(x, y, z);
+                    let Some(id) = self.index(pos) else {
+                        continue;
+                    };
+                    let new = new_pos(pos);
+                    let idx = new.x + new.z * new_size.x + new.y * new_size.x * new_size.z;
+                    data[idx as usize] = id;
+                }
+            }
+        }
+        Self {
+            palette,
+            data,
+            width: new_size.x as usize,
+            length: new_size.z as usize,
+            columns: false,
+        }
+    }
+
+    /// The `Biomes` container of a version 3 schematic for a region, which has a biome for
+    /// every block
+    fn to_nbt(&self, min: IVec3, size: IVec3) -> Value {
+        // Blocks outside the stored biomes get the default one
+        let (palette, data) = palette_data(size, |pos| {
+            self.get(min + pos).unwrap_or("minecraft:plains")
+        });
+        compound([("Palette", palette), ("Data", data)])
+    }
+}
+
+/// Decodes the varint encoded palette indices used by Sponge schematics
+fn read_varints(bytes: &[i8]) -> Vec<usize> {
+    let mut values = Vec::new();
+    let mut value = 0;
+    let mut shift = 0;
+    for &byte in bytes {
+        value |= (byte as u8 as usize & 0x7F) << shift;
+        if byte as u8 & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    values
+}
+
+fn write_varints(values: impl IntoIterator<Item = usize>) -> Vec<i8> {
+    let mut bytes = Vec::new();
+    for mut value in values {
+        while value >= 0x80 {
+            bytes.push(((value & 0x7F) | 0x80) as u8 as i8);
+            value >>= 7;
+        }
+        bytes.push(value as u8 as i8);
+    }
+    bytes
+}
+
+/// Parts of a Sponge schematic that `mc_schems` doesn't expose
+pub struct SchematicNbt {
+    /// Block entities by their position relative to the schematic origin
+    pub block_entities: HashMap<IVec3, BlockEntity>,
+    /// Entities, which version 1 schematics don't have
+    pub entities: Vec<SchematicEntity>,
+    /// World position of the first block
+    pub offset: IVec3,
+    /// World position WorldEdit copied the schematic from, which pasting is relative to
+    pub origin: IVec3,
+    /// Biomes, which version 1 schematics and most exports without them don't have
+    pub biomes: Option<SchematicBiomes>,
+    /// Version of the game the schematic was saved in, which version 1 schematics don't have
+    pub data_version: Option<i32>,
+}
+
+impl SchematicNbt {
+    pub fn read(bytes: &[u8]) -> Result<Self> {
+        let blob = Blob::from_gzip_reader(&mut &bytes[..])?;
+
+        // Version 3 nests everything in a `Schematic` compound and moves block entities into
+        // the `Blocks` container, while versions 1 and 2 have them at the root
+        let schematic = match blob.get("Schematic") {
+            Some(Value::Compound(schematic)) => Some(schematic),
+            _ => None,
+        };
+        let root = |name: &str| match schematic {
+            Some(schematic) => schematic.get(name),
+            None => blob.get(name),
+        };
+        let block_entities = match root("Blocks") {
+            Some(Value::Compound(blocks)) => blocks.get("BlockEntities"),
+            _ => root("BlockEntities").or_else(|| root("TileEntities")),
+        };
+
+        let block_entities = match block_entities {
+            Some(Value::List(list)) => list.iter().filter_map(read_block_entity).collect(),
+            _ => HashMap::new(),
+        };
+        let entities = match root("Entities") {
+            Some(Value::List(list)) => list.iter().filter_map(read_entity).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut offset = int_vec(root("Offset")).unwrap_or_default();
+        let metadata = match root("Metadata") {
+            Some(Value::Compound(metadata)) => Some(metadata),
+            _ => None,
+        };
+        let origin = if schematic.is_some() {
+            // Version 3 stores the offset relative to where WorldEdit copied from, and that
+            // origin in its metadata
+            let origin = match metadata.and_then(|metadata| metadata.get("WorldEdit")) {
+                Some(Value::Compound(worldedit)) => int_vec(worldedit.get("Origin")),
+                _ => None,
+            };
+            let origin = origin.unwrap_or_default();
+            offset += origin;
+            origin
+        } else {
+            // Older versions store the offset in the world, and WorldEdit adds the offset from
+            // where it copied from to the metadata
+            let axis = |name: &str| match metadata.and_then(|metadata| metadata.get(name)) {
+                Some(Value::Int(value)) => Some(*value),
+                _ => None,
+            };
+            match (axis("WEOffsetX"), axis("WEOffsetY"), axis("WEOffsetZ")) {
+                (Some(x), Some(y), Some(z)) => offset - IVec3::new(x, y, z),
+                _ => offset,
+            }
+        };
+
+        let dimension = |name: &str| match root(name) {
+            Some(Value::Short(size)) => *size as u16 as usize,
+            _ => 0,
+        };
+        let (width, length) = (dimension("Width"), dimension("Length"));
+        let biomes = match (root("Biomes"), root("BiomePalette"), root("BiomeData")) {
+            (Some(Value::Compound(biomes)), _, _) => biomes
+                .get("Palette")
+                .zip(biomes.get("Data"))
+                .and_then(|(palette, data)| {
+                    SchematicBiomes::read(palette, data, width, length, false)
+                }),
+            (None, Some(palette), Some(data)) => {
+                SchematicBiomes::read(palette, data, width, length, true)
+            }
+            _ => None,
+        };
+
+        let data_version = match root("DataVersion") {
+            Some(Value::Int(version)) => Some(*version),
+            _ => None,
+        };
+
+        Ok(Self {
+            block_entities,
+            entities,
+            offset,
+            origin,
+            biomes,
+            data_version,
+        })
+    }
+}
+
+fn int_vec(value: Option<&Value>) -> Option<IVec3> {
+    match value {
+        Some(Value::IntArray(values)) => Some(IVec3::new(
+            *values.first()?,
+            *values.get(1)?,
+            *values.get(2)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Version 3 puts the data in its own compound, older versions store it next to the id
+fn entry_data(entry: &Value) -> Value {
+    let Value::Compound(compound) = entry else {
+        return entry.clone();
+    };
+    match compound.get("Data") {
+        Some(data @ Value::Compound(_)) => data.clone(),
+        _ => {
+            let mut data = compound.clone();
+            data.remove("Pos");
+            data.remove("Id");
+            Value::Compound(data)
+        }
+    }
+}
+
+fn read_block_entity(value: &Value) -> Option<(IVec3, BlockEntity)> {
+    let Value::Compound(compound) = value else {
+        return None;
+    };
+    let Some(Value::IntArray(pos)) = compound.get("Pos") else {
+        return None;
+    };
+    let Some(Value::String(id)) = compound.get("Id") else {
+        return None;
+    };
+    let pos = IVec3::new(*pos.first()?, *pos.get(1)?, *pos.get(2)?);
+    let data = entry_data(value);
+
+    Some((
+        pos,
+        BlockEntity {
+            id: id.clone(),
+            data,
+        },
+    ))
+}
+
+fn read_entity(value: &Value) -> Option<SchematicEntity> {
+    let Value::Compound(compound) = value else {
+        return None;
+    };
+    let Some(Value::List(pos)) = compound.get("Pos") else {
+        return None;
+    };
+    let Some(Value::String(id)) = compound.get("Id") else {
+        return None;
+    };
+    let coord = |idx: usize| match pos.get(idx) {
+        Some(Value::Double(coord)) => Some(*coord as f32),
+        _ => None,
+    };
+
+    Some(SchematicEntity {
+        id: id.clone(),
+        pos: Vec3::new(coord(0)?, coord(1)?, coord(2)?),
+        data: entry_data(value),
+    })
+}
+
+fn compound<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Compound(
+        entries
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
+
+fn int_array(pos: IVec3) -> Value {
+    Value::IntArray(pos.to_array().to_vec())
+}
+
+/// Palette and varint encoded data of the blocks or biomes in a box, in the YZX order of Sponge
+/// schematics
+fn palette_data<'a>(size: IVec3, at: impl Fn(IVec3) -> &'a str) -> (Value, Value) {
+    let mut palette: HashMap<&str, usize> = HashMap::new();
+    let mut data = Vec::with_capacity((size.x * size.y * size.z).max(0) as usize);
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let next = palette.len();
+                data.push(*palette.entry(at(IVec3::new(x, y, z))).or_insert(next));
+            }
+        }
+    }
+    let palette = palette
+        .into_iter()
+        .map(|(name, id)| (name.to_string(), Value::Int(id as i32)))
+        .collect();
+    (
+        Value::Compound(palette),
+        Value::ByteArray(write_varints(data)),
+    )
+}
+
+/// Writes a box of the world as a version 3 Sponge schematic, keeping the block entities,
+/// entities, biomes and WorldEdit origin inside it. `min` is the first block of the box.
+pub fn write_schematic(block_world: &BlockWorld, min: IVec3, size: IVec3) -> Result<Vec<u8>> {
+    let inside = |pos: IVec3| pos.cmpge(min).all() && pos.cmplt(min + size).all();
+    let (palette, data) = palette_data(size, |pos| {
+        let pos = min + pos;
+        block_world
+            .blocks
+            .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize)
+    });
+
+    let block_entities = block_world
+        .block_entities
+        .iter()
+        .filter(|(pos, _)| inside(**pos))
+        .map(|(pos, block_entity)| {
+            compound([
+                ("Pos", int_array(*pos - min)),
+                ("Id", Value::String(block_entity.id.clone())),
+                ("Data", block_entity.data.clone()),
+            ])
+        })
+        .collect();
+    let entities = block_world
+        .schematic_entities
+        .iter()
+        .filter(|entity| inside(entity.pos.floor().as_ivec3()))
+        .map(|entity| {
+            let pos = (entity.pos - min.as_vec3()).to_array();
+            compound([
+                (
+                    "Pos",
+                    Value::List(pos.map(|coord| Value::Double(coord as f64)).to_vec()),
+                ),
+                ("Id", Value::String(entity.id.clone())),
+                ("Data", entity.data.clone()),
+            ])
+        })
+        .collect();
+
+    // Version 3 stores the offset relative to the WorldEdit origin
+    let offset = block_world.offset + min - block_world.origin;
+    let mut schematic = vec![
+        ("Version", Value::Int(3)),
+        ("Width", Value::Short(size.x as i16)),
+        ("Height", Value::Short(size.y as i16)),
+        ("Length", Value::Short(size.z as i16)),
+        ("Offset", int_array(offset)),
+        (
+            "Metadata",
+            compound([(
+                "WorldEdit",
+                compound([("Origin", int_array(block_world.origin))]),
+            )]),
+        ),
+        (
+            "Blocks",
+            compound([
+                ("Palette", palette),
+                ("Data", data),
+                ("BlockEntities", Value::List(block_entities)),
+            ]),
+        ),
+        ("Entities", Value::List(entities)),
+    ];
+    if let Some(version) = block_world.data_version {
+        schematic.push(("DataVersion", Value::Int(version)));
+    }
+    if let Some(biomes) = &block_world.biomes {
+        schematic.push(("Biomes", biomes.to_nbt(min, size)));
+    }
+
+    let mut blob = Blob::new();
+    blob.insert(
+        "Schematic",
+        Value::Compound(
+            schematic
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        ),
+    )?;
+    let mut bytes = Vec::new();
+    blob.to_gzip_writer(&mut bytes)?;
+    Ok(bytes)
+}
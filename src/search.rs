@@ -0,0 +1,235 @@
+//! Search window for block queries, which lists the matches with their coordinates, highlights
+//! them through walls and flies the camera from one match to the next.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::lang::Lang;
+use crate::origin::CoordinateSpace;
+use crate::query::{BlockQuery, BlockTags};
+use crate::{AppLoadState, BlockWorld, McCamera, SchematicRoot};
+
+/// Most matches highlighted at once, to keep the gizmos cheap
+const MAX_HIGHLIGHTS: usize = 4096;
+/// How far in front of a match the camera stops, in blocks
+const VIEW_DISTANCE: f32 = 6.0;
+/// How long the camera takes to fly to a match, in seconds
+const FLIGHT_TIME: f32 = 0.6;
+
+#[derive(Resource, Default)]
+pub struct Search {
+    query: String,
+    matches: Vec<IVec3>,
+    /// Index of the match the camera flew to last
+    current: Option<usize>,
+    error: Option<String>,
+}
+
+impl Search {
+    fn run(&mut self, tags: &BlockTags, block_world: &BlockWorld) {
+        self.current = None;
+        match BlockQuery::parse(&self.query, tags) {
+            Ok(query) => {
+                self.matches = query.find(&block_world.blocks);
+                self.error = None;
+            }
+            Err(err) => {
+                self.matches.clear();
+                self.error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Searches for a query from elsewhere, like the blocks of a row of the material list
+    pub fn set_query(&mut self, query: String, tags: &BlockTags, block_world: &BlockWorld) {
+        self.query = query;
+        self.run(tags, block_world);
+    }
+
+    /// Moves to the next match, or to the previous one for negative steps
+    fn step(&mut self, steps: isize) -> Option<IVec3> {
+        let len = self.matches.len() as isize;
+        if len == 0 {
+            return None;
+        }
+        let current = match self.current {
+            Some(current) => (current as isize + steps).rem_euclid(len),
+            None if steps > 0 => 0,
+            None => len - 1,
+        } as usize;
+        self.current = Some(current);
+        Some(self.matches[current])
+    }
+}
+
+/// Asks the camera to fly to a block of the schematic
+#[derive(Event)]
+struct FlyToBlock(IVec3);
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct SearchGizmos;
+
+/// N flies to the next match and Shift+N to the previous one
+fn search_keys(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut search: ResMut<Search>,
+    mut fly_to: EventWriter<FlyToBlock>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() || !keyboard_input.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if let Some(pos) = search.step(if shift { -1 } else { 1 }) {
+        fly_to.send(FlyToBlock(pos));
+    }
+}
+
+fn search_window(
+    mut contexts: EguiContexts,
+    mut search: ResMut<Search>,
+    mut fly_to: EventWriter<FlyToBlock>,
+    tags: Res<BlockTags>,
+    block_world: Res<BlockWorld>,
+    space: Res<CoordinateSpace>,
+    lang: Res<Lang>,
+) {
+    let offset = space.offset(&block_world);
+    egui::Window::new("Search")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut search.query)
+                        .hint_text("minecraft:repeater[delay=4]"),
+                );
+                let entered =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if ui.button("Search").clicked() || entered {
+                    search.run(&tags, &block_world);
+                }
+            });
+            if let Some(error) = &search.error {
+                ui.colored_label(egui::Color32::RED, error);
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label(format!("{} matches", search.matches.len()));
+                let mut step = 0;
+                if ui.button("Previous").clicked() {
+                    step = -1;
+                }
+                if ui.button("Next (N)").clicked() {
+                    step = 1;
+                }
+                if let Some(pos) = (step != 0).then(|| search.step(step)).flatten() {
+                    fly_to.send(FlyToBlock(pos));
+                }
+            });
+            if search.matches.len() > MAX_HIGHLIGHTS {
+                ui.label(format!("Only the first {} are highlighted", MAX_HIGHLIGHTS));
+            }
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::vertical().max_height(240.0).show_rows(
+                ui,
+                row_height,
+                search.matches.len(),
+                |ui, rows| {
+                    for idx in rows {
+                        let pos = search.matches[idx];
+                        let block = block_world.blocks.get_block_at(
+                            pos.x as usize,
+                            pos.y as usize,
+                            pos.z as usize,
+                        );
+                        let shown = pos + offset;
+                        let name = lang.block_name(block);
+                        let text = format!("{} {} {}  {}", shown.x, shown.y, shown.z, name);
+                        let response = ui.selectable_label(search.current == Some(idx), text);
+                        if response.on_hover_text(block).clicked() {
+                            search.current = Some(idx);
+                            fly_to.send(FlyToBlock(pos));
+                        }
+                    }
+                },
+            );
+        });
+}
+
+fn highlight_matches(
+    mut gizmos: Gizmos<SearchGizmos>,
+    search: Res<Search>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let root = root.compute_matrix();
+    for (idx, pos) in search.matches.iter().take(MAX_HIGHLIGHTS).enumerate() {
+        let center = pos.as_vec3() + Vec3::splat(0.5);
+        let (scale, color) = if search.current == Some(idx) {
+            (1.1, Color::srgb(1.0, 1.0, 0.0))
+        } else {
+            (1.02, Color::srgb(1.0, 0.5, 0.0))
+        };
+        let transform =
+            Mat4::from_scale_rotation_translation(Vec3::splat(scale), Quat::IDENTITY, center);
+        gizmos.cuboid(root * transform, color);
+    }
+}
+
+/// Flies the camera towards a match, keeping the direction it looks in so the match ends up in
+/// the middle of the screen
+fn fly_to_match(
+    mut events: EventReader<FlyToBlock>,
+    mut flight: Local<Option<(Vec3, Vec3, f32)>>,
+    time: Res<Time>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+    mut camera: Query<&mut Transform, With<McCamera>>,
+) {
+    let (Ok(root), Ok(mut transform)) = (root.get_single(), camera.get_single_mut()) else {
+        return;
+    };
+    if let Some(FlyToBlock(pos)) = events.read().last() {
+        let center = root.transform_point(pos.as_vec3() + Vec3::splat(0.5));
+        let to = center - *transform.forward() * VIEW_DISTANCE;
+        *flight = Some((transform.translation, to, 0.0));
+    }
+
+    let Some((from, to, elapsed)) = flight.as_mut() else {
+        return;
+    };
+    *elapsed += time.delta_seconds();
+    let t = (*elapsed / FLIGHT_TIME).min(1.0);
+    // Ease in and out so the camera doesn't jump
+    transform.translation = from.lerp(*to, t * t * (3.0 - 2.0 * t));
+    if t >= 1.0 {
+        *flight = None;
+    }
+}
+
+pub struct SearchPlugin;
+
+impl Plugin for SearchPlugin {
+    fn build(&self, app: &mut App) {
+        // Matches are drawn on top so the ones inside the build can be seen
+        app.insert_gizmo_config(
+            SearchGizmos,
+            GizmoConfig {
+                line_width: 2.0,
+                depth_bias: -1.0,
+                ..default()
+            },
+        )
+        .init_resource::<Search>()
+        .add_event::<FlyToBlock>()
+        .add_systems(
+            Update,
+            (search_keys, search_window, highlight_matches, fly_to_match)
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
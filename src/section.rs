@@ -0,0 +1,345 @@
+//! Slicing the schematic by Y level and cutting it down to a section box, to study a build
+//! layer by layer. The cut is made by the block material's shader, so moving it only updates
+//! a few uniforms no matter how large the schematic is.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use bevy_fly_camera::FlyCamera;
+
+use crate::block::{Block, BlockMaterial, BlockPalette, SectionClip};
+use crate::editor::Editor;
+use crate::selection::Selection;
+use crate::xray::{block_shown, Xray};
+use crate::{AppLoadState, BlockWorld, McCamera, SchematicRoot};
+
+/// How close to a handle on screen the cursor has to be to grab it, in logical pixels
+const HANDLE_GRAB_RADIUS: f32 = 16.0;
+/// Radius of the handles, in blocks
+const HANDLE_RADIUS: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SliceMode {
+    #[default]
+    Off,
+    /// Layers up to and including the slice level
+    Below,
+    /// Only the slice level, with the layer below ghosted
+    Layer,
+}
+
+impl SliceMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            SliceMode::Off => "Off",
+            SliceMode::Below => "Below",
+            SliceMode::Layer => "Layer",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            SliceMode::Off => SliceMode::Below,
+            SliceMode::Below => SliceMode::Layer,
+            SliceMode::Layer => SliceMode::Off,
+        }
+    }
+}
+
+/// Which part of the schematic is drawn, in schematic blocks
+#[derive(Resource, Clone, PartialEq)]
+pub struct SectionView {
+    pub slice: SliceMode,
+    /// Y level the slice is at
+    pub level: i32,
+    /// Whether the section box cuts the schematic
+    pub boxed: bool,
+    /// Corners of the section box, with the maximum excluded
+    pub min: IVec3,
+    pub max: IVec3,
+    /// Size of the schematic, which the slice and the box stay within
+    pub size: IVec3,
+}
+
+impl SectionView {
+    pub fn for_size(size: IVec3) -> Self {
+        Self {
+            slice: SliceMode::Off,
+            level: size.y - 1,
+            boxed: false,
+            min: IVec3::ZERO,
+            max: size,
+            size,
+        }
+    }
+
+    /// The clip the block materials get
+    pub fn clip(&self) -> SectionClip {
+        let mut clip = SectionClip::default();
+        if self.boxed {
+            clip.cut(self.min.as_vec3(), self.max.as_vec3());
+        }
+        let level = self.level as f32;
+        match self.slice {
+            SliceMode::Off => {}
+            SliceMode::Below => clip.max.y = clip.max.y.min(level + 1.0),
+            SliceMode::Layer => {
+                clip.min.y = clip.min.y.max(level - 1.0);
+                clip.max.y = clip.max.y.min(level + 1.0);
+                clip.ghost_layer(self.level - 1);
+            }
+        }
+        clip
+    }
+
+    fn step_level(&mut self, steps: i32) {
+        self.level = (self.level + steps).clamp(0, self.size.y - 1);
+    }
+
+    /// Centers of the faces of the section box, with the axis and side each one moves
+    fn handles(&self) -> impl Iterator<Item = ((usize, bool), Vec3)> {
+        let (min, max) = (self.min.as_vec3(), self.max.as_vec3());
+        let center = (min + max) / 2.0;
+        (0..3).flat_map(move |axis| {
+            [false, true].map(|is_max| {
+                let mut pos = center;
+                pos[axis] = if is_max { max[axis] } else { min[axis] };
+                ((axis, is_max), pos)
+            })
+        })
+    }
+}
+
+/// Rows of the settings grid for the slice and the section box
+pub fn section_settings(ui: &mut egui::Ui, section: &mut ResMut<SectionView>) {
+    let mut view = section.clone();
+
+    ui.label("Slice (L)");
+    ui.horizontal(|ui| {
+        for mode in [SliceMode::Off, SliceMode::Below, SliceMode::Layer] {
+            ui.radio_value(&mut view.slice, mode, mode.name());
+        }
+    });
+    ui.end_row();
+
+    ui.label("Slice Level (PgUp/PgDn)");
+    ui.add(egui::Slider::new(&mut view.level, 0..=view.size.y - 1));
+    ui.end_row();
+
+    ui.label("Section Box");
+    ui.checkbox(&mut view.boxed, "");
+    ui.end_row();
+
+    if view.boxed {
+        for (axis, name) in ["X", "Y", "Z"].into_iter().enumerate() {
+            ui.label(format!("Section {}", name));
+            ui.horizontal(|ui| {
+                let max = view.max[axis];
+                ui.add(egui::DragValue::new(&mut view.min[axis]).range(0..=max - 1));
+                let min = view.min[axis];
+                let size = view.size[axis];
+                ui.add(egui::DragValue::new(&mut view.max[axis]).range(min + 1..=size));
+            });
+            ui.end_row();
+        }
+    }
+
+    section.set_if_neq(view);
+}
+
+/// L cycles the slice mode and Page Up and Page Down move the slice
+fn slice_keys(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut view: ResMut<SectionView>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyL) {
+        view.slice = view.slice.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        view.step_level(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        view.step_level(-1);
+    }
+}
+
+/// Drags a face of the section box along its axis, following the cursor
+#[allow(clippy::too_many_arguments)]
+fn drag_handles(
+    mut contexts: EguiContexts,
+    mut dragged: Local<Option<(usize, bool)>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform, &FlyCamera), With<McCamera>>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+    editor: Res<Editor>,
+    selection: Res<Selection>,
+    mut view: ResMut<SectionView>,
+) {
+    let (Ok(window), Ok((camera, camera_transform, fly_camera)), Ok(root)) =
+        (window.get_single(), camera.get_single(), root.get_single())
+    else {
+        return;
+    };
+    // Clicks break blocks in edit mode and set corners in select mode
+    let other_tool = editor.enabled || selection.enabled;
+    if !view.boxed || fly_camera.enabled || other_tool || !mouse_input.pressed(MouseButton::Left) {
+        *dragged = None;
+        return;
+    }
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let root = root.compute_matrix();
+
+    if mouse_input.just_pressed(MouseButton::Left) && !contexts.ctx_mut().is_pointer_over_area() {
+        *dragged = view
+            .handles()
+            .filter_map(|(face, pos)| {
+                let pos = root.transform_point3(pos);
+                let distance = camera
+                    .world_to_viewport(camera_transform, pos)?
+                    .distance(cursor);
+                (distance < HANDLE_GRAB_RADIUS).then_some((face, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(face, _)| face);
+    }
+    let Some((axis, is_max)) = *dragged else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+
+    // Closest point on the line through the handle along its axis to the cursor ray
+    let to_schematic = root.inverse();
+    let origin = to_schematic.transform_point3(ray.origin);
+    let dir = to_schematic.transform_vector3(*ray.direction).normalize();
+    let (_, handle) = view
+        .handles()
+        .find(|(face, _)| *face == (axis, is_max))
+        .unwrap();
+    let axis_dir = Vec3::AXES[axis];
+    let offset = handle - origin;
+    let cos = axis_dir.dot(dir);
+    let denom = 1.0 - cos * cos;
+    // The cursor can't move the face while looking straight along its axis
+    if denom < 1.0e-4 {
+        return;
+    }
+    let along = (cos * dir.dot(offset) - axis_dir.dot(offset)) / denom;
+    let value = (handle[axis] + along).round() as i32;
+
+    let new = if is_max {
+        value.clamp(view.min[axis] + 1, view.size[axis])
+    } else {
+        value.clamp(0, view.max[axis] - 1)
+    };
+    let current = if is_max {
+        view.max[axis]
+    } else {
+        view.min[axis]
+    };
+    if new != current {
+        if is_max {
+            view.max[axis] = new;
+        } else {
+            view.min[axis] = new;
+        }
+    }
+}
+
+fn draw_section_box(
+    mut gizmos: Gizmos,
+    view: Res<SectionView>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    if !view.boxed {
+        return;
+    }
+    let root = root.compute_matrix();
+    let (min, max) = (view.min.as_vec3(), view.max.as_vec3());
+    let color = Color::srgb(0.2, 0.8, 1.0);
+    gizmos.cuboid(
+        root * Mat4::from_scale_rotation_translation(max - min, Quat::IDENTITY, (min + max) / 2.0),
+        color,
+    );
+    for (_, pos) in view.handles() {
+        gizmos.sphere(
+            root.transform_point3(pos),
+            Quat::IDENTITY,
+            HANDLE_RADIUS,
+            color,
+        );
+    }
+}
+
+/// Passes the section to the block materials, and hides the meshes of block entities that
+/// aren't drawn with them, like sign text and banners, along with cut away blocks
+fn apply_section(
+    view: Res<SectionView>,
+    xray: Res<Xray>,
+    block_world: Res<BlockWorld>,
+    palette: Res<BlockPalette>,
+    mut materials: ResMut<Assets<BlockMaterial>>,
+    mut blocks: Query<(&Block, &mut Visibility)>,
+) {
+    let clip = view.clip();
+    // Materials are checked every frame since tinted ones are added as blocks come in. Ghosted
+    // materials of the x-ray keep ghosting everything.
+    let ids: Vec<_> = materials.ids().collect();
+    for id in ids {
+        let Some(material) = materials.get(id) else {
+            continue;
+        };
+        let clip = SectionClip {
+            ghost_all: material.extension.ghost_all,
+            ..clip.clone()
+        };
+        if material.extension != clip {
+            materials.get_mut(id).unwrap().extension = clip;
+        }
+    }
+
+    if !view.is_changed() {
+        return;
+    }
+    for pos in block_world.block_entities.keys() {
+        let Some(&entity) = block_world.entities.get(&pos.as_uvec3()) else {
+            continue;
+        };
+        let Ok((block, mut visibility)) = blocks.get_mut(entity) else {
+            continue;
+        };
+        let name = &palette.blocks()[block.index()];
+        let shown = if block_shown(&xray, &clip, &block_world, *pos, name) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != shown {
+            *visibility = shown;
+        }
+    }
+}
+
+pub struct SectionPlugin;
+
+impl Plugin for SectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (slice_keys, drag_handles, draw_section_box, apply_section)
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Two corner selection like WorldEdit's wand. In select mode left click sets the first corner
+//! and right click the second, and the selected box can be exported as its own schematic.
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_fly_camera::FlyCamera;
+use mc_schems::Blocks;
+
+use crate::editor::Editor;
+use crate::origin::CoordinateSpace;
+use crate::picking::TargetedBlock;
+use crate::schematic::write_schematic;
+use crate::{AppLoadState, BlockWorld, McCamera, SchematicRoot};
+
+#[derive(Resource, Default)]
+pub struct Selection {
+    pub enabled: bool,
+    pub first: Option<IVec3>,
+    pub second: Option<IVec3>,
+}
+
+impl Selection {
+    /// The first block and the size of the selected box, once both corners are set
+    pub fn region(&self) -> Option<(IVec3, IVec3)> {
+        let (first, second) = (self.first?, self.second?);
+        let min = first.min(second);
+        Some((min, first.max(second) - min + 1))
+    }
+}
+
+/// Blocks in a box that aren't air
+fn count_blocks(blocks: &Blocks, min: IVec3, size: IVec3) -> u64 {
+    let mut count = 0;
+    for x in min.x..min.x + size.x {
+        for y in min.y..min.y + size.y {
+            for z in min.z..min.z + size.z {
+                if blocks.get_block_at(x as usize, y as usize, z as usize) != "minecraft:air" {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct SelectionGizmos;
+
+struct SelectionWindow {
+    export_path: String,
+    /// Non-air blocks of the region they were counted for
+    block_count: Option<((IVec3, IVec3), u64)>,
+    status: Option<String>,
+}
+
+impl Default for SelectionWindow {
+    fn default() -> Self {
+        Self {
+            export_path: "selection.schem".to_string(),
+            block_count: None,
+            status: None,
+        }
+    }
+}
+
+/// V toggles select mode, which takes over the mouse from edit mode
+fn selection_keys(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<Selection>,
+    mut editor: ResMut<Editor>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() || !keyboard_input.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+    selection.enabled = !selection.enabled;
+    if selection.enabled {
+        editor.enabled = false;
+    }
+}
+
+fn select_with_mouse(
+    mut contexts: EguiContexts,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    targeted: Res<TargetedBlock>,
+    fly_camera: Query<&FlyCamera, With<McCamera>>,
+    mut selection: ResMut<Selection>,
+) {
+    if !selection.enabled {
+        return;
+    }
+    let grabbed = fly_camera.get_single().is_ok_and(|camera| camera.enabled);
+    if !grabbed && contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    let Some(pos) = targeted.0 else {
+        return;
+    };
+    if mouse_input.just_pressed(MouseButton::Left) {
+        selection.first = Some(pos);
+    }
+    if mouse_input.just_pressed(MouseButton::Right) {
+        selection.second = Some(pos);
+    }
+}
+
+fn selection_window(
+    mut contexts: EguiContexts,
+    mut window: Local<SelectionWindow>,
+    mut selection: ResMut<Selection>,
+    mut editor: ResMut<Editor>,
+    block_world: Res<BlockWorld>,
+    space: Res<CoordinateSpace>,
+) {
+    let window = &mut *window;
+    let offset = space.offset(&block_world);
+    egui::Window::new("Selection")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if ui
+                .checkbox(&mut selection.enabled, "Select mode (V)")
+                .changed()
+            {
+                editor.enabled &= !selection.enabled;
+            }
+            ui.label("Left click sets the first corner and right click the second");
+            for (name, corner) in [("First", selection.first), ("Second", selection.second)] {
+                let text = match corner {
+                    Some(pos) => {
+                        let shown = pos + offset;
+                        format!("{} corner: {} {} {}", name, shown.x, shown.y, shown.z)
+                    }
+                    None => format!("{} corner: not set", name),
+                };
+                ui.label(text);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Select all").clicked() {
+                    let (sx, sy, sz) = block_world.blocks.size();
+                    selection.first = Some(IVec3::ZERO);
+                    selection.second = Some(IVec3::new(sx as i32, sy as i32, sz as i32) - 1);
+                }
+                if ui.button("Clear").clicked() {
+                    selection.first = None;
+                    selection.second = None;
+                }
+            });
+
+            let Some(region @ (min, size)) = selection.region() else {
+                return;
+            };
+            // Counting goes through every block, so it's only redone when something changed
+            let stale = window.block_count.map(|(counted, _)| counted) != Some(region);
+            if stale || block_world.is_changed() {
+                let count = count_blocks(&block_world.blocks, min, size);
+                window.block_count = Some((region, count));
+            }
+            let count = window.block_count.map_or(0, |(_, count)| count);
+            ui.separator();
+            ui.label(format!("Size: {} × {} × {}", size.x, size.y, size.z));
+            ui.label(format!("Volume: {} blocks", size.x * size.y * size.z));
+            ui.label(format!("Blocks: {}", count));
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut window.export_path);
+                if ui.button("Export").clicked() {
+                    let result = write_schematic(&block_world, min, size)
+                        .and_then(|bytes| Ok(fs::write(&window.export_path, bytes)?));
+                    window.status = Some(match result {
+                        Ok(()) => format!("Exported to {}", window.export_path),
+                        Err(err) => format!("Could not export: {}", err),
+                    });
+                }
+            });
+            if let Some(status) = &window.status {
+                ui.label(status);
+            }
+        });
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos<SelectionGizmos>,
+    selection: Res<Selection>,
+    root: Query<&GlobalTransform, With<SchematicRoot>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let root = root.compute_matrix();
+    let corners = [
+        (selection.first, Color::srgb(1.0, 0.3, 0.3)),
+        (selection.second, Color::srgb(0.3, 0.5, 1.0)),
+    ];
+    for (corner, color) in corners {
+        if let Some(pos) = corner {
+            let center = pos.as_vec3() + Vec3::splat(0.5);
+            let transform =
+                Mat4::from_scale_rotation_translation(Vec3::splat(1.02), Quat::IDENTITY, center);
+            gizmos.cuboid(root * transform, color);
+        }
+    }
+    if let Some((min, size)) = selection.region() {
+        let (min, size) = (min.as_vec3(), size.as_vec3());
+        let transform =
+            Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, min + size / 2.0);
+        gizmos.cuboid(root * transform, Color::srgb(1.0, 0.9, 0.2));
+    }
+}
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        // The box is drawn on top so it stays visible inside the build
+        app.insert_gizmo_config(
+            SelectionGizmos,
+            GizmoConfig {
+                line_width: 2.0,
+                depth_bias: -1.0,
+                ..default()
+            },
+        )
+        .init_resource::<Selection>()
+        .add_systems(
+            Update,
+            (
+                selection_keys,
+                select_with_mouse,
+                selection_window,
+                draw_selection,
+            )
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder};
+use bevy::prelude::*;
+
+use crate::time_of_day::Sun;
+
+/// Cascade shadow map configuration for the sun
+#[derive(Resource, Debug, Clone)]
+pub struct ShadowSettings {
+    pub num_cascades: usize,
+    /// Distance from the camera after which nothing receives shadows
+    pub maximum_distance: f32,
+    pub depth_bias: f32,
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self::for_bounds(Vec3::splat(64.0))
+    }
+}
+
+impl ShadowSettings {
+    /// Picks defaults which cover the whole schematic from anywhere around it without wasting
+    /// shadow map resolution on empty space.
+    pub fn for_bounds(size: Vec3) -> Self {
+        let diagonal = size.length();
+        // Leave room for the camera to back off from the schematic a bit
+        let maximum_distance = (diagonal * 1.5).clamp(32.0, 2048.0);
+        // Small schematics fit comfortably in a single cascade
+        let num_cascades = match maximum_distance {
+            d if d <= 64.0 => 1,
+            d if d <= 256.0 => 2,
+            d if d <= 1024.0 => 3,
+            _ => 4,
+        };
+        Self {
+            num_cascades,
+            maximum_distance,
+            // Depth precision gets worse the more distance a cascade covers
+            depth_bias: DirectionalLight::DEFAULT_SHADOW_DEPTH_BIAS
+                * (maximum_distance / 256.0).max(1.0),
+            normal_bias: DirectionalLight::DEFAULT_SHADOW_NORMAL_BIAS,
+        }
+    }
+
+    fn cascade_config(&self) -> CascadeShadowConfig {
+        let num_cascades = self.num_cascades.max(1);
+        let maximum_distance = self.maximum_distance.max(1.0);
+        CascadeShadowConfigBuilder {
+            num_cascades,
+            minimum_distance: 0.1,
+            maximum_distance,
+            // Each cascade covers four times the distance of the previous one
+            first_cascade_far_bound: (maximum_distance / 4f32.powi(num_cascades as i32 - 1))
+                .max(0.2),
+            overlap_proportion: 0.2,
+        }
+        .build()
+    }
+}
+
+fn apply_shadow_settings(
+    settings: Res<ShadowSettings>,
+    mut sun: Query<(&mut DirectionalLight, &mut CascadeShadowConfig), With<Sun>>,
+) {
+    for (mut light, mut cascade_config) in sun.iter_mut() {
+        light.shadow_depth_bias = settings.depth_bias;
+        light.shadow_normal_bias = settings.normal_bias;
+        *cascade_config = settings.cascade_config();
+    }
+}
+
+pub struct ShadowPlugin;
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowSettings>().add_systems(
+            Update,
+            apply_shadow_settings.run_if(resource_changed::<ShadowSettings>),
+        );
+    }
+}
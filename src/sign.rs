@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use nbt::Value;
+
+use crate::block::{init_blocks, sign_text_layout, Block, SignTextLayout};
+use crate::dye::{self, rgb_color};
+use crate::resources::font::{McFont, TextCanvas};
+use crate::schematic::BlockEntity;
+use crate::{AppLoadState, BlockWorld};
+
+/// Named colors used in text components, or a hex color like `#ff0000`
+fn text_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        return u32::from_str_radix(hex, 16).ok().map(rgb_color);
+    }
+    let rgb = match name {
+        "black" => 0x000000,
+        "dark_blue" => 0x0000AA,
+        "dark_green" => 0x00AA00,
+        "dark_aqua" => 0x00AAAA,
+        "dark_red" => 0xAA0000,
+        "dark_purple" => 0xAA00AA,
+        "gold" => 0xFFAA00,
+        "gray" => 0xAAAAAA,
+        "dark_gray" => 0x555555,
+        "blue" => 0x5555FF,
+        "green" => 0x55FF55,
+        "aqua" => 0x55FFFF,
+        "red" => 0xFF5555,
+        "light_purple" => 0xFF55FF,
+        "yellow" => 0xFFFF55,
+        "white" => 0xFFFFFF,
+        _ => return None,
+    };
+    Some(rgb_color(rgb))
+}
+
+/// Flattens a JSON text component into characters with their color, if they have one
+fn flatten_component(
+    component: &serde_json::Value,
+    color: Option<Color>,
+    out: &mut Vec<(char, Option<Color>)>,
+) {
+    match component {
+        serde_json::Value::String(text) => out.extend(text.chars().map(|c| (c, color))),
+        serde_json::Value::Array(components) => {
+            for component in components {
+                flatten_component(component, color, out);
+            }
+        }
+        serde_json::Value::Object(component) => {
+            let color = component
+                .get("color")
+                .and_then(|color| color.as_str())
+                .and_then(text_color)
+                .or(color);
+            // Translations aren't resolved, so at least show the key
+            let text = component
+                .get("text")
+                .or_else(|| component.get("translate"))
+                .and_then(|text| text.as_str());
+            if let Some(text) = text {
+                out.extend(text.chars().map(|c| (c, color)));
+            }
+            if let Some(extra) = component.get("extra") {
+                flatten_component(extra, color, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_line(line: &str) -> Vec<(char, Option<Color>)> {
+    let mut chars = Vec::new();
+    match serde_json::from_str(line) {
+        Ok(component) => flatten_component(&component, None, &mut chars),
+        // Very old signs store plain text
+        Err(_) => chars.extend(line.chars().map(|c| (c, None))),
+    }
+    chars
+}
+
+struct SignText {
+    lines: Vec<Vec<(char, Option<Color>)>>,
+    color: Color,
+    glowing: bool,
+}
+
+impl SignText {
+    fn read(block_entity: &BlockEntity, front: bool) -> Option<Self> {
+        let key = if front { "front_text" } else { "back_text" };
+        let (messages, color, glowing) = match block_entity.get(key) {
+            Some(Value::Compound(side)) => {
+                let Some(Value::List(messages)) = side.get("messages") else {
+                    return None;
+                };
+                let messages = messages
+                    .iter()
+                    .filter_map(|message| match message {
+                        Value::String(message) => Some(message.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                (messages, side.get("color"), side.get("has_glowing_text"))
+            }
+            // Before 1.20 signs only had text on the front
+            _ if front => {
+                let messages = ["Text1", "Text2", "Text3", "Text4"]
+                    .iter()
+                    .filter_map(|key| match block_entity.get(key) {
+                        Some(Value::String(message)) => Some(message.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                (
+                    messages,
+                    block_entity.get("Color"),
+                    block_entity.get("GlowingText"),
+                )
+            }
+            _ => return None,
+        };
+
+        let lines: Vec<_> = messages.into_iter().map(parse_line).collect();
+        if lines.iter().all(Vec::is_empty) {
+            return None;
+        }
+        let color = match color {
+            Some(Value::String(color)) => dye::text_color(color),
+            _ => None,
+        };
+        Some(Self {
+            lines,
+            color: color.unwrap_or(Color::BLACK),
+            glowing: matches!(glowing, Some(Value::Byte(1..))),
+        })
+    }
+
+    /// The color text is drawn with when it's not glowing, and the outline color when it is
+    fn dark_color(&self) -> Color {
+        let color = self.color.to_srgba();
+        if self.glowing && color == Srgba::BLACK {
+            return rgb_color(0xF0EBCC);
+        }
+        Color::srgb(color.red * 0.4, color.green * 0.4, color.blue * 0.4)
+    }
+
+    fn rasterize(&self, font: &mut McFont, layout: &SignTextLayout) -> (TextCanvas, Vec2) {
+        // Leave room for the outline of glowing text
+        let size = Vec2::new(
+            layout.max_line_width + 2.0,
+            layout.line_height * self.lines.len().max(4) as f32 + 2.0,
+        );
+        let mut canvas = TextCanvas::new(size.x as u32, size.y as u32);
+
+        let dark_color = self.dark_color();
+        let default_color = if self.glowing { self.color } else { dark_color };
+        let total_height = layout.line_height * 4.0 / 2.0;
+        for (i, line) in self.lines.iter().enumerate() {
+            let width = font.width(line.iter().map(|(c, _)| *c));
+            let x = (size.x - width) / 2.0;
+            let y = size.y / 2.0 + i as f32 * layout.line_height - total_height;
+            if self.glowing {
+                for (dx, dy) in [
+                    (-1.0, -1.0),
+                    (0.0, -1.0),
+                    (1.0, -1.0),
+                    (-1.0, 0.0),
+                    (1.0, 0.0),
+                    (-1.0, 1.0),
+                    (0.0, 1.0),
+                    (1.0, 1.0),
+                ] {
+                    let outline = line.iter().map(|(c, _)| (*c, dark_color));
+                    font.draw(&mut canvas, outline, x + dx, y + dy);
+                }
+            }
+            let text = line
+                .iter()
+                .map(|(c, color)| (*c, color.unwrap_or(default_color)));
+            font.draw(&mut canvas, text, x, y);
+        }
+        (canvas, size)
+    }
+}
+
+/// The plain text of the lines on one side of a sign, without formatting
+pub fn sign_lines(block_entity: &BlockEntity, front: bool) -> Option<Vec<String>> {
+    let text = SignText::read(block_entity, front)?;
+    let lines = text
+        .lines
+        .iter()
+        .map(|line| line.iter().map(|(c, _)| *c).collect())
+        .collect();
+    Some(lines)
+}
+
+/// Creates a quad in block pixels covering text of the given size
+fn text_quad(transform: Mat4, size: Vec2) -> Mesh {
+    let half = size / 2.0;
+    let corners = [
+        Vec3::new(-half.x, half.y, 0.0),
+        Vec3::new(half.x, half.y, 0.0),
+        Vec3::new(half.x, -half.y, 0.0),
+        Vec3::new(-half.x, -half.y, 0.0),
+    ];
+    let positions: Vec<[f32; 3]> = corners
+        .iter()
+        .map(|&corner| transform.transform_point3(corner).to_array())
+        .collect();
+    let normal = transform.transform_vector3(Vec3::Z).normalize_or_zero();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![normal.to_array(); 4])
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+    )
+    .with_inserted_indices(Indices::U32(vec![0, 1, 2, 2, 3, 0]))
+}
+
+/// Draws the text of signs as they're spawned, both when the schematic is loaded and when
+/// they're placed or replaced while editing
+fn spawn_sign_text(
+    mut commands: Commands,
+    block_world: Res<BlockWorld>,
+    blocks: Query<(Entity, &Block), Changed<Block>>,
+    font: Option<ResMut<McFont>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // Signs are left without text when the font couldn't be loaded
+    let Some(mut font) = font else {
+        return;
+    };
+    for (entity, block) in blocks.iter() {
+        if !block.is_world_block(entity, &block_world) {
+            continue;
+        }
+        let pos = block.pos();
+        let Some(block_entity) = block_world.block_entities.get(&pos) else {
+            continue;
+        };
+        if !block_entity.id.ends_with("sign") {
+            continue;
+        }
+        let block = block_world
+            .blocks
+            .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+        let Some(layout) = sign_text_layout(block) else {
+            warn!(
+                "Sign block entity at {} has unexpected block {}",
+                pos, block
+            );
+            continue;
+        };
+
+        for (front, transform) in [(true, layout.front), (false, layout.back)] {
+            let Some(text) = SignText::read(block_entity, front) else {
+                continue;
+            };
+            let (canvas, size) = text.rasterize(&mut font, &layout);
+            let material = StandardMaterial {
+                base_color_texture: Some(images.add(canvas.into_image())),
+                perceptual_roughness: 1.0,
+                reflectance: 0.0,
+                alpha_mode: AlphaMode::Mask(0.5),
+                // Glowing text ignores lighting
+                unlit: text.glowing,
+                ..default()
+            };
+            let text_entity = commands
+                .spawn(PbrBundle {
+                    mesh: meshes.add(text_quad(transform, size)),
+                    material: materials.add(material),
+                    ..default()
+                })
+                .id();
+            commands.entity(entity).add_child(text_entity);
+        }
+    }
+}
+
+pub struct SignPlugin;
+
+impl Plugin for SignPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            spawn_sign_text
+                .after(init_blocks)
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_atmosphere::prelude::*;
+
+use crate::environment::dimension_has_sky_cycle;
+
+/// Number of ticks in a full Minecraft day
+pub const TICKS_PER_DAY: f32 = 24000.0;
+
+const SUN_ILLUMINANCE: f32 = 2000.0;
+const MOON_ILLUMINANCE: f32 = 200.0;
+const DAY_AMBIENT_BRIGHTNESS: f32 = 1000.0;
+/// Ambient brightness at night, standing in for starlight
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 150.0;
+
+#[derive(Resource)]
+pub struct TimeOfDay {
+    /// Time in ticks, following Minecraft: 0 is sunrise, 6000 is noon and 18000 is midnight
+    pub ticks: f32,
+    /// Whether time advances automatically
+    pub cycle: bool,
+    /// Ticks advanced per second while cycling
+    pub speed: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            ticks: 6000.0,
+            cycle: false,
+            // Minecraft runs at 20 ticks per second
+            speed: 20.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Unit vector pointing towards the sun. The sun rises along +X and sets along -X.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.ticks / TICKS_PER_DAY * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// How much of the daylight is visible, from 0 at night to 1 during the day. The transition
+    /// happens while the sun is close to the horizon.
+    pub fn daylight(&self) -> f32 {
+        let height = self.sun_direction().y;
+        ((height + 0.1) / 0.3).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Component)]
+pub struct Sun;
+
+#[derive(Component)]
+struct Moon;
+
+fn setup_sky(mut commands: Commands, time_of_day: Res<TimeOfDay>) {
+    commands.insert_resource(AtmosphereModel::new(Nishita {
+        sun_position: time_of_day.sun_direction(),
+        ..default()
+    }));
+    commands.spawn((
+        Sun,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        Moon,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::srgb(0.6, 0.7, 1.0),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn advance_time(mut time_of_day: ResMut<TimeOfDay>, time: Res<Time>) {
+    if !time_of_day.cycle {
+        return;
+    }
+    let ticks = time_of_day.ticks + time_of_day.speed * time.delta_seconds();
+    time_of_day.ticks = ticks.rem_euclid(TICKS_PER_DAY);
+}
+
+fn update_sky(
+    time_of_day: Res<TimeOfDay>,
+    mut atmosphere: AtmosphereMut<Nishita>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut sun: Query<(&mut Transform, &mut DirectionalLight), (With<Sun>, Without<Moon>)>,
+    mut moon: Query<(&mut Transform, &mut DirectionalLight), (With<Moon>, Without<Sun>)>,
+) {
+    let sun_direction = time_of_day.sun_direction();
+    let daylight = time_of_day.daylight();
+
+    atmosphere.sun_position = sun_direction;
+    ambient_light.brightness = NIGHT_AMBIENT_BRIGHTNESS.lerp(DAY_AMBIENT_BRIGHTNESS, daylight);
+
+    // The sun and moon move in the XY plane, so Z is always a valid up vector
+    let (mut transform, mut light) = sun.single_mut();
+    *transform = Transform::IDENTITY.looking_to(-sun_direction, Vec3::Z);
+    light.illuminance = SUN_ILLUMINANCE * daylight;
+
+    let (mut transform, mut light) = moon.single_mut();
+    *transform = Transform::IDENTITY.looking_to(sun_direction, Vec3::Z);
+    light.illuminance = MOON_ILLUMINANCE * (1.0 - daylight);
+}
+
+pub struct TimeOfDayPlugin;
+
+impl Plugin for TimeOfDayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .add_systems(Startup, setup_sky.run_if(dimension_has_sky_cycle))
+            .add_systems(
+                Update,
+                (
+                    advance_time,
+                    update_sky.run_if(resource_changed::<TimeOfDay>),
+                )
+                    .chain()
+                    .run_if(dimension_has_sky_cycle),
+            );
+    }
+}
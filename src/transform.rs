@@ -0,0 +1,626 @@
+//! Turning and mirroring blocks along with their states, so stairs, rails, redstone wire and
+//! everything else that points somewhere keeps pointing the right way. The whole schematic can be
+//! transformed when it's loaded and the selection interactively.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContexts};
+use clap::ValueEnum;
+use mc_schems::Blocks;
+use nbt::Value;
+
+use crate::block::{parse_props, split_block_state};
+use crate::editor::{BlockChange, EditBlocks};
+use crate::schematic::{SchematicEntity, SchematicNbt};
+use crate::selection::Selection;
+use crate::{AppLoadState, BlockWorld};
+
+/// Horizontal directions in clockwise order, seen from above
+const HORIZONTAL: [&str; 4] = ["north", "east", "south", "west"];
+
+/// How a block state property changes when the block is turned or mirrored
+#[derive(Clone, Copy)]
+enum PropertyKind {
+    /// A direction like `north` or `up`
+    Direction,
+    /// Directions joined by `_`, like `north_up` for jigsaws and crafters
+    Directions,
+    /// `x`, `y` or `z`
+    Axis,
+    /// One of 16 steps clockwise from south, for signs, banners and heads
+    Rotation16,
+    /// Stair shapes like `inner_left` or rail shapes like `ascending_east` and `north_west`
+    Shape,
+    /// `left` or `right`, which mirroring swaps, like door hinges and chest halves
+    Handedness,
+}
+
+/// Properties that change when blocks are turned or mirrored, by name. Together with the
+/// properties named after a horizontal direction, like the connections of redstone wire, fences,
+/// walls and panes, these are all the ways vanilla blocks store directions.
+const PROPERTIES: &[(&str, PropertyKind)] = &[
+    ("facing", PropertyKind::Direction),
+    ("orientation", PropertyKind::Directions),
+    ("axis", PropertyKind::Axis),
+    ("rotation", PropertyKind::Rotation16),
+    ("shape", PropertyKind::Shape),
+    ("hinge", PropertyKind::Handedness),
+    ("type", PropertyKind::Handedness),
+];
+
+/// Clockwise turn seen from above
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    None,
+    #[value(name = "90")]
+    Clockwise90,
+    #[value(name = "180")]
+    Clockwise180,
+    #[value(name = "270")]
+    Clockwise270,
+}
+
+impl Rotation {
+    const ALL: [(Rotation, &'static str); 4] = [
+        (Rotation::None, "0°"),
+        (Rotation::Clockwise90, "90°"),
+        (Rotation::Clockwise180, "180°"),
+        (Rotation::Clockwise270, "270°"),
+    ];
+
+    fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::Clockwise270 => 3,
+        }
+    }
+}
+
+/// Axis whose coordinates are flipped by a mirror, so `x` swaps east and west
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorAxis {
+    X,
+    Z,
+}
+
+/// A mirror followed by a turn, like vanilla applies them to structures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockTransform {
+    pub mirror: Option<MirrorAxis>,
+    pub rotation: Rotation,
+}
+
+impl BlockTransform {
+    pub fn is_identity(&self) -> bool {
+        self.mirror.is_none() && self.rotation == Rotation::None
+    }
+
+    fn swaps_axes(&self) -> bool {
+        self.rotation.quarter_turns() % 2 == 1
+    }
+
+    /// Size of a box after the transform
+    pub fn size(&self, size: IVec3) -> IVec3 {
+        if self.swaps_axes() {
+            IVec3::new(size.z, size.y, size.x)
+        } else {
+            size
+        }
+    }
+
+    /// Where a point of a box of `size` ends up in the transformed box, both relative to the
+    /// first corner of the box
+    fn point(&self, point: Vec3, size: Vec3) -> Vec3 {
+        let mut point = match self.mirror {
+            Some(MirrorAxis::X) => Vec3::new(size.x - point.x, point.y, point.z),
+            Some(MirrorAxis::Z) => Vec3::new(point.x, point.y, size.z - point.z),
+            None => point,
+        };
+        let mut size = size;
+        for _ in 0..self.rotation.quarter_turns() {
+            point = Vec3::new(size.z - point.z, point.y, point.x);
+            size = Vec3::new(size.z, size.y, size.x);
+        }
+        point
+    }
+
+    /// Where a block of a box of `size` ends up in the transformed box
+    pub fn position(&self, pos: IVec3, size: IVec3) -> IVec3 {
+        let center = pos.as_vec3() + Vec3::splat(0.5);
+        self.point(center, size.as_vec3()).floor().as_ivec3()
+    }
+
+    /// A horizontal direction after the transform, other directions stay as they are
+    fn direction<'a>(&self, dir: &'a str) -> &'a str {
+        let Some(mut idx) = HORIZONTAL.iter().position(|horizontal| *horizontal == dir) else {
+            return dir;
+        };
+        match self.mirror {
+            Some(MirrorAxis::X) if idx % 2 == 1 => idx = 4 - idx,
+            Some(MirrorAxis::Z) if idx % 2 == 0 => idx = (idx + 2) % 4,
+            _ => {}
+        }
+        HORIZONTAL[(idx + self.rotation.quarter_turns()) % 4]
+    }
+
+    /// Steps of 1/16 of a turn clockwise from south after the transform
+    fn rotation16(&self, rotation: u32) -> u32 {
+        let rotation = match self.mirror {
+            Some(MirrorAxis::X) => 16 - rotation,
+            Some(MirrorAxis::Z) => 24 - rotation,
+            None => rotation,
+        };
+        (rotation + 4 * self.rotation.quarter_turns() as u32) % 16
+    }
+
+    /// Yaw of an entity after the transform, in degrees clockwise from south
+    fn yaw(&self, yaw: f32) -> f32 {
+        let yaw = match self.mirror {
+            Some(MirrorAxis::X) => -yaw,
+            Some(MirrorAxis::Z) => 180.0 - yaw,
+            None => yaw,
+        };
+        (yaw + 90.0 * self.rotation.quarter_turns() as f32).rem_euclid(360.0)
+    }
+
+    fn rail_shape(&self, shape: &str) -> String {
+        if let Some(dir) = shape.strip_prefix("ascending_") {
+            return format!("ascending_{}", self.direction(dir));
+        }
+        let Some((a, b)) = shape.split_once('_') else {
+            return shape.to_string();
+        };
+        // Vanilla names put north and south first, like `north_south` and `south_east`
+        let mut dirs = [self.direction(a), self.direction(b)];
+        let order = ["north", "south", "east", "west"];
+        dirs.sort_by_key(|dir| order.iter().position(|other| other == dir));
+        dirs.join("_")
+    }
+
+    fn property(&self, kind: PropertyKind, value: &str) -> String {
+        let mirrored = self.mirror.is_some();
+        match kind {
+            PropertyKind::Direction => self.direction(value).to_string(),
+            PropertyKind::Directions => value
+                .split('_')
+                .map(|dir| self.direction(dir))
+                .collect::<Vec<_>>()
+                .join("_"),
+            PropertyKind::Axis => match value {
+                "x" if self.swaps_axes() => "z".to_string(),
+                "z" if self.swaps_axes() => "x".to_string(),
+                _ => value.to_string(),
+            },
+            PropertyKind::Rotation16 => match value.parse() {
+                Ok(rotation) => self.rotation16(rotation).to_string(),
+                Err(_) => value.to_string(),
+            },
+            PropertyKind::Shape if value.ends_with("left") || value.ends_with("right") => {
+                // Mirroring swaps which side the corner of a stair is on
+                let (shape, side) = value.split_once('_').unwrap_or(("", value));
+                match side {
+                    "left" if mirrored => format!("{}_right", shape),
+                    "right" if mirrored => format!("{}_left", shape),
+                    _ => value.to_string(),
+                }
+            }
+            PropertyKind::Shape => self.rail_shape(value),
+            PropertyKind::Handedness => match value {
+                "left" if mirrored => "right".to_string(),
+                "right" if mirrored => "left".to_string(),
+                _ => value.to_string(),
+            },
+        }
+    }
+
+    /// A block state after the transform, keeping the order of its properties
+    pub fn block_state(&self, block: &str) -> String {
+        let (name, props) = split_block_state(block);
+        if props.is_empty() || self.is_identity() {
+            return block.to_string();
+        }
+        let values = parse_props(props);
+        let props: Vec<_> = props
+            .split(',')
+            .filter_map(|prop| prop.split_once('='))
+            .map(|(key, value)| {
+                let value = if HORIZONTAL.contains(&key) {
+                    // Connections move to the direction they point in after the transform
+                    let from = HORIZONTAL.iter().find(|dir| self.direction(dir) == key);
+                    let from = from.and_then(|dir| values.get(dir));
+                    from.copied().unwrap_or(value).to_string()
+                } else {
+                    match PROPERTIES.iter().find(|(name, _)| *name == key) {
+                        Some(&(_, kind)) => self.property(kind, value),
+                        None => value.to_string(),
+                    }
+                };
+                format!("{}={}", key, value)
+            })
+            .collect();
+        format!("{}[{}]", name, props.join(","))
+    }
+
+    /// Moves an entity within a schematic of `size` and turns it along with the blocks
+    fn entity(&self, entity: &mut SchematicEntity, size: Vec3) {
+        entity.pos = self.point(entity.pos, size);
+        let Value::Compound(data) = &mut entity.data else {
+            return;
+        };
+        if let Some(Value::List(rotation)) = data.get_mut("Rotation") {
+            if let Some(Value::Float(yaw)) = rotation.first_mut() {
+                *yaw = self.yaw(*yaw);
+            }
+        }
+        // Item frames store a 3D direction and paintings a horizontal one, like the yaw
+        let directions: &[&str] = if entity.id.ends_with("item_frame") {
+            &["down", "up", "north", "south", "west", "east"]
+        } else {
+            &["south", "west", "north", "east"]
+        };
+        for key in ["Facing", "facing"] {
+            if let Some(Value::Byte(facing)) = data.get_mut(key) {
+                let Some(&dir) = directions.get(*facing as usize) else {
+                    continue;
+                };
+                let dir = self.direction(dir);
+                if let Some(idx) = directions.iter().position(|other| *other == dir) {
+                    *facing = idx as i8;
+                }
+            }
+        }
+    }
+}
+
+/// Transforms the whole schematic as it's loaded, along with its block entities, entities and
+/// biomes. The first block stays where it is in the world.
+pub fn transform_schematic(
+    blocks: &Blocks,
+    schematic_nbt: &mut SchematicNbt,
+    transform: BlockTransform,
+) -> Blocks {
+    let (sx, sy, sz) = blocks.size();
+    let size = IVec3::new(sx as i32, sy as i32, sz as i32);
+    let new_size = transform.size(size);
+    let (nx, ny, nz) = (
+        new_size.x as usize,
+        new_size.y as usize,
+        new_size.z as usize,
+    );
+    let mut transformed = Blocks::new(nx, ny, nz);
+    let mut states: HashMap<&str, String> = HashMap::new();
+    for x in 0..sx {
+        for y in 0..sy {
+            for z in 0..sz {
+                let block = blocks.get_block_at(x, y, z);
+                let state = states
+                    .entry(block)
+                    .or_insert_with(|| transform.block_state(block));
+                let pos = transform.position(IVec3::new(x as i32, y as i32, z as i32), size);
+                transformed.set_block_at(pos.x as usize, pos.y as usize, pos.z as usize, state);
+            }
+        }
+    }
+
+    schematic_nbt.block_entities = schematic_nbt
+        .block_entities
+        .drain()
+        .map(|(pos, block_entity)| (transform.position(pos, size), block_entity))
+        .collect();
+    for entity in &mut schematic_nbt.entities {
+        transform.entity(entity, size.as_vec3());
+    }
+    if let Some(biomes) = &schematic_nbt.biomes {
+        let moved = biomes.moved(size, new_size, |pos| transform.position(pos, size));
+        schematic_nbt.biomes = Some(moved);
+    }
+    transformed
+}
+
+/// The edit that transforms a box of the world in place along with the entities in it, keeping
+/// its first corner. Blocks that end up outside the schematic are cut off, and their number is
+/// returned along with the edit.
+fn transform_region(
+    block_world: &BlockWorld,
+    min: IVec3,
+    size: IVec3,
+    transform: BlockTransform,
+) -> (EditBlocks, usize) {
+    let (sx, sy, sz) = block_world.blocks.size();
+    let bounds = IVec3::new(sx as i32, sy as i32, sz as i32);
+    let mut cleared = Vec::new();
+    let mut placed = Vec::new();
+    let mut cut_off = 0;
+    for x in 0..size.x {
+        for y in 0..size.y {
+            for z in 0..size.z {
+                let pos = min + IVec3::new(x, y, z);
+                let block =
+                    block_world
+                        .blocks
+                        .get_block_at(pos.x as usize, pos.y as usize, pos.z as usize);
+                if block == "minecraft:air" {
+                    continue;
+                }
+                cleared.push(BlockChange::new(pos, "minecraft:air"));
+                let new_pos = min + transform.position(pos - min, size);
+                if new_pos.cmpge(bounds).any() {
+                    cut_off += 1;
+                    continue;
+                }
+                placed.push(BlockChange {
+                    pos: new_pos,
+                    block: transform.block_state(block),
+                    block_entity: block_world.block_entities.get(&pos).cloned(),
+                });
+            }
+        }
+    }
+    // Everything is cleared first so blocks moving onto each other don't get lost
+    cleared.extend(placed);
+
+    let inside = |pos: Vec3| {
+        let pos = pos.floor().as_ivec3();
+        pos.cmpge(min).all() && pos.cmplt(min + size).all()
+    };
+    let mut moved = false;
+    let mut entities = block_world.schematic_entities.clone();
+    for entity in entities.iter_mut().filter(|entity| inside(entity.pos)) {
+        entity.pos -= min.as_vec3();
+        transform.entity(entity, size.as_vec3());
+        entity.pos += min.as_vec3();
+        moved = true;
+    }
+    let edit = EditBlocks {
+        entities: moved.then_some(entities),
+        ..EditBlocks::new(cleared)
+    };
+    (edit, cut_off)
+}
+
+#[derive(Default)]
+struct TransformWindow {
+    transform: BlockTransform,
+    status: Option<String>,
+}
+
+fn transform_window(
+    mut contexts: EguiContexts,
+    mut window: Local<TransformWindow>,
+    mut selection: ResMut<Selection>,
+    mut edits: EventWriter<EditBlocks>,
+    block_world: Res<BlockWorld>,
+) {
+    let window = &mut *window;
+    egui::Window::new("Rotate & Mirror")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Mirror");
+                let mirror = &mut window.transform.mirror;
+                ui.selectable_value(mirror, None, "None");
+                ui.selectable_value(mirror, Some(MirrorAxis::X), "X");
+                ui.selectable_value(mirror, Some(MirrorAxis::Z), "Z");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Then turn clockwise");
+                for (rotation, name) in Rotation::ALL {
+                    ui.selectable_value(&mut window.transform.rotation, rotation, name);
+                }
+            });
+
+            let (sx, sy, sz) = block_world.blocks.size();
+            let region = selection.region();
+            let target = if region.is_some() {
+                "Applies to the selection"
+            } else {
+                "Applies to the whole schematic, without a selection"
+            };
+            ui.label(target);
+            let (min, size) =
+                region.unwrap_or((IVec3::ZERO, IVec3::new(sx as i32, sy as i32, sz as i32)));
+            let new_size = window.transform.size(size);
+            // The schematic can't change its size in place, that's only done when it's loaded
+            let resizes_schematic = region.is_none() && new_size != size;
+            if resizes_schematic {
+                ui.label("Turning a schematic that isn't square is only possible with --rotate");
+            } else if new_size != size {
+                ui.label("Turning a box that isn't square cuts off what ends up outside it");
+            }
+
+            let apply = egui::Button::new("Apply");
+            let enabled = !window.transform.is_identity() && !resizes_schematic;
+            if ui.add_enabled(enabled, apply).clicked() {
+                let (edit, cut_off) = transform_region(&block_world, min, size, window.transform);
+                edits.send(edit);
+                window.status = (cut_off > 0)
+                    .then(|| format!("{} blocks ended up outside the schematic", cut_off));
+                if region.is_some() {
+                    // The turned box may not fit, so the selection is kept inside the schematic
+                    let bounds = IVec3::new(sx as i32, sy as i32, sz as i32);
+                    selection.first = Some(min);
+                    selection.second = Some((min + new_size).min(bounds) - 1);
+                }
+            }
+            if let Some(status) = &window.status {
+                ui.label(status);
+            }
+        });
+}
+
+pub struct TransformPlugin;
+
+impl Plugin for TransformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            transform_window.run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: &[&str] = &[
+        "minecraft:stone",
+        "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left,waterlogged=false]",
+        "minecraft:oak_stairs[facing=north,half=top,shape=outer_right,waterlogged=false]",
+        "minecraft:rail[shape=ascending_north,waterlogged=false]",
+        "minecraft:rail[shape=north_east,waterlogged=false]",
+        "minecraft:powered_rail[powered=false,shape=east_west,waterlogged=false]",
+        "minecraft:redstone_wire[east=side,north=up,power=0,south=none,west=side]",
+        "minecraft:oak_sign[rotation=5,waterlogged=false]",
+        "minecraft:oak_log[axis=x]",
+        "minecraft:jigsaw[orientation=north_up]",
+        "minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]",
+        "minecraft:chest[facing=west,type=left,waterlogged=false]",
+        "minecraft:oak_fence[east=true,north=false,south=true,waterlogged=false,west=false]",
+    ];
+
+    fn turn() -> BlockTransform {
+        BlockTransform {
+            mirror: None,
+            rotation: Rotation::Clockwise90,
+        }
+    }
+
+    fn mirror(axis: MirrorAxis) -> BlockTransform {
+        BlockTransform {
+            mirror: Some(axis),
+            rotation: Rotation::None,
+        }
+    }
+
+    #[test]
+    fn four_turns_keep_states() {
+        for state in STATES {
+            let mut turned = state.to_string();
+            for _ in 0..4 {
+                turned = turn().block_state(&turned);
+            }
+            assert_eq!(turned, *state);
+        }
+    }
+
+    #[test]
+    fn mirroring_twice_keeps_states() {
+        for axis in [MirrorAxis::X, MirrorAxis::Z] {
+            for state in STATES {
+                let mirrored = mirror(axis).block_state(&mirror(axis).block_state(state));
+                assert_eq!(mirrored, *state);
+            }
+        }
+    }
+
+    #[test]
+    fn rail_shapes() {
+        let rail = |shape: &str| format!("minecraft:rail[shape={},waterlogged=false]", shape);
+        for (shape, turned) in [
+            ("north_south", "east_west"),
+            ("east_west", "north_south"),
+            ("north_east", "south_east"),
+            ("south_east", "south_west"),
+            ("south_west", "north_west"),
+            ("north_west", "north_east"),
+            ("ascending_north", "ascending_east"),
+            ("ascending_west", "ascending_north"),
+        ] {
+            assert_eq!(turn().block_state(&rail(shape)), rail(turned));
+        }
+        let mirrored = mirror(MirrorAxis::X).block_state(&rail("north_east"));
+        assert_eq!(mirrored, rail("north_west"));
+    }
+
+    #[test]
+    fn mirrored_stairs_swap_sides() {
+        let stairs = |facing: &str, shape: &str| {
+            format!(
+                "minecraft:oak_stairs[facing={},half=bottom,shape={}]",
+                facing, shape
+            )
+        };
+        let x = mirror(MirrorAxis::X);
+        assert_eq!(
+            x.block_state(&stairs("east", "inner_left")),
+            stairs("west", "inner_right")
+        );
+        assert_eq!(
+            x.block_state(&stairs("north", "outer_right")),
+            stairs("north", "outer_left")
+        );
+        assert_eq!(
+            x.block_state(&stairs("north", "straight")),
+            stairs("north", "straight")
+        );
+        let z = mirror(MirrorAxis::Z);
+        assert_eq!(
+            z.block_state(&stairs("north", "inner_right")),
+            stairs("south", "inner_left")
+        );
+        // Turning doesn't change handedness
+        assert_eq!(
+            turn().block_state(&stairs("north", "inner_left")),
+            stairs("east", "inner_left")
+        );
+    }
+
+    #[test]
+    fn sign_rotations() {
+        let sign = |rotation: u32| format!("minecraft:oak_sign[rotation={}]", rotation);
+        for rotation in 0..16 {
+            let turned = turn().block_state(&sign(rotation));
+            assert_eq!(turned, sign((rotation + 4) % 16));
+            let mirrored = mirror(MirrorAxis::X).block_state(&sign(rotation));
+            assert_eq!(mirrored, sign((16 - rotation) % 16));
+            let mirrored = mirror(MirrorAxis::Z).block_state(&sign(rotation));
+            assert_eq!(mirrored, sign((24 - rotation) % 16));
+        }
+    }
+
+    #[test]
+    fn wire_connections_follow_directions() {
+        let wire = "minecraft:redstone_wire[east=side,north=up,power=0,south=none,west=side]";
+        assert_eq!(
+            turn().block_state(wire),
+            "minecraft:redstone_wire[east=up,north=side,power=0,south=side,west=none]"
+        );
+        assert_eq!(
+            mirror(MirrorAxis::Z).block_state(wire),
+            "minecraft:redstone_wire[east=side,north=none,power=0,south=up,west=side]"
+        );
+    }
+
+    #[test]
+    fn positions_stay_in_the_box() {
+        let size = IVec3::new(3, 2, 5);
+        for x in 0..size.x {
+            for y in 0..size.y {
+                for z in 0..size.z {
+                    let pos = IVec3::new(x, y, z);
+                    let (mut turned, mut turned_size) = (pos, size);
+                    for _ in 0..4 {
+                        turned = turn().position(turned, turned_size);
+                        turned_size = turn().size(turned_size);
+                        assert!(turned.cmpge(IVec3::ZERO).all());
+                        assert!(turned.cmplt(turned_size).all());
+                    }
+                    assert_eq!(turned, pos);
+
+                    for axis in [MirrorAxis::X, MirrorAxis::Z] {
+                        let mirrored = mirror(axis).position(pos, size);
+                        assert_eq!(mirror(axis).position(mirrored, size), pos);
+                    }
+                }
+            }
+        }
+        assert_eq!(turn().position(IVec3::ZERO, size), IVec3::new(4, 0, 0));
+        let mirrored = mirror(MirrorAxis::X).position(IVec3::ZERO, size);
+        assert_eq!(mirrored, IVec3::new(2, 0, 0));
+    }
+}
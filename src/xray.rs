@@ -0,0 +1,360 @@
+//! X-ray mode, which hides chosen block types or draws them as faint ghosts to show the
+//! redstone buried inside a build. The list of blocks can be edited live and saved as a preset.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::block::{split_block_state, Block, BlockLook, BlockPalette, SectionClip};
+use crate::icon::BlockIcons;
+use crate::query::wildcard_matches;
+use crate::section::SectionView;
+use crate::{AppLoadState, BlockWorld, StartupWarnings};
+
+/// Blocks that make up redstone circuits, including the containers they interact with
+const REDSTONE_COMPONENTS: &[&str] = &[
+    "redstone_wire",
+    "redstone_torch",
+    "redstone_wall_torch",
+    "redstone_block",
+    "redstone_lamp",
+    "repeater",
+    "comparator",
+    "observer",
+    "piston",
+    "sticky_piston",
+    "piston_head",
+    "moving_piston",
+    "dispenser",
+    "dropper",
+    "crafter",
+    "hopper",
+    "chest",
+    "trapped_chest",
+    "barrel",
+    "*shulker_box",
+    "lectern",
+    "lever",
+    "*_button",
+    "*_pressure_plate",
+    "tripwire_hook",
+    "tripwire",
+    "*rail",
+    "target",
+    "daylight_detector",
+    "note_block",
+    "*sculk_sensor",
+    "*copper_bulb",
+    "slime_block",
+    "honey_block",
+    "*command_block",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XrayAction {
+    #[default]
+    Hide,
+    Ghost,
+}
+
+/// A named filter, as stored in the presets file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct XrayPreset {
+    pub name: String,
+    /// Block names, where `*` matches any text and the `minecraft:` namespace is optional
+    pub blocks: Vec<String>,
+    pub action: XrayAction,
+    /// Applies the action to every block that isn't listed instead
+    pub only: bool,
+}
+
+fn builtin_presets() -> Vec<XrayPreset> {
+    vec![
+        XrayPreset {
+            name: "Hide Stone, Concrete and Glass".to_string(),
+            blocks: ["stone", "*concrete", "*glass", "*glass_pane"]
+                .map(String::from)
+                .to_vec(),
+            action: XrayAction::Hide,
+            only: false,
+        },
+        XrayPreset {
+            name: "Only Redstone Components".to_string(),
+            blocks: REDSTONE_COMPONENTS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            action: XrayAction::Ghost,
+            only: true,
+        },
+    ]
+}
+
+#[derive(Resource)]
+pub struct Xray {
+    pub enabled: bool,
+    /// The filter being edited, which is saved under its name
+    pub filter: XrayPreset,
+    /// Presets from the presets file followed by the built in ones
+    presets: Vec<XrayPreset>,
+    presets_file: PathBuf,
+}
+
+impl Xray {
+    /// Loads the saved presets, if there are any
+    pub fn load(presets_file: PathBuf, warnings: &mut StartupWarnings) -> Self {
+        let mut presets = Vec::new();
+        if let Ok(json) = fs::read_to_string(&presets_file) {
+            match serde_json::from_str(&json) {
+                Ok(saved) => presets = saved,
+                Err(err) => warnings.push(format!(
+                    "Could not read {}: {}",
+                    presets_file.display(),
+                    err
+                )),
+            }
+        }
+        let builtin = builtin_presets();
+        for preset in &builtin {
+            if !presets
+                .iter()
+                .any(|saved: &XrayPreset| saved.name == preset.name)
+            {
+                presets.push(preset.clone());
+            }
+        }
+        Self {
+            enabled: false,
+            filter: builtin[0].clone(),
+            presets,
+            presets_file,
+        }
+    }
+
+    /// Stores the current filter as a preset, replacing one with the same name
+    fn save_preset(&mut self) -> Result<()> {
+        let filter = self.filter.clone();
+        match self
+            .presets
+            .iter_mut()
+            .find(|preset| preset.name == filter.name)
+        {
+            Some(preset) => *preset = filter,
+            None => self.presets.insert(0, filter),
+        }
+        fs::write(
+            &self.presets_file,
+            serde_json::to_string_pretty(&self.presets)?,
+        )?;
+        Ok(())
+    }
+
+    /// What happens to a block state while x-ray is on
+    pub fn action(&self, block: &str) -> Option<XrayAction> {
+        let name = split_block_state(block).0.trim_start_matches("minecraft:");
+        if !self.enabled || name == "air" {
+            return None;
+        }
+        let listed =
+            self.filter.blocks.iter().any(|pattern| {
+                wildcard_matches(pattern.trim().trim_start_matches("minecraft:"), name)
+            });
+        (listed != self.filter.only).then_some(self.filter.action)
+    }
+}
+
+/// Whether a block of the schematic is drawn at all. Block entities that are cut away by the
+/// section are hidden as a whole as well, since their extra meshes like sign text aren't drawn
+/// with the block material.
+pub fn block_shown(
+    xray: &Xray,
+    clip: &SectionClip,
+    block_world: &BlockWorld,
+    pos: IVec3,
+    block: &str,
+) -> bool {
+    if xray.action(block) == Some(XrayAction::Hide) {
+        return false;
+    }
+    !block_world.block_entities.contains_key(&pos) || clip.contains(pos)
+}
+
+fn apply_xray(
+    xray: Res<Xray>,
+    section: Res<SectionView>,
+    block_world: Res<BlockWorld>,
+    palette: Res<BlockPalette>,
+    mut blocks: Query<(&Block, &mut Visibility, &mut BlockLook)>,
+    changed: Query<(), Changed<Block>>,
+) {
+    // Blocks placed or replaced while editing need the x-ray applied too
+    if !xray.is_changed() && changed.is_empty() {
+        return;
+    }
+    let clip = section.clip();
+    let actions: Vec<_> = palette
+        .blocks()
+        .iter()
+        .map(|block| xray.action(block))
+        .collect();
+
+    for (pos, &entity) in block_world.entities.iter() {
+        let Ok((block, mut visibility, mut look)) = blocks.get_mut(entity) else {
+            continue;
+        };
+        let name = &palette.blocks()[block.index()];
+        let shown = if block_shown(&xray, &clip, &block_world, pos.as_ivec3(), name) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != shown {
+            *visibility = shown;
+        }
+        let ghost = actions[block.index()] == Some(XrayAction::Ghost);
+        if look.ghost != ghost {
+            look.ghost = ghost;
+        }
+    }
+}
+
+fn toggle_xray(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut xray: ResMut<Xray>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyX) && !contexts.ctx_mut().wants_keyboard_input() {
+        xray.enabled = !xray.enabled;
+    }
+}
+
+fn xray_window(
+    mut contexts: EguiContexts,
+    mut new_block: Local<String>,
+    mut names: Local<Vec<String>>,
+    mut xray: ResMut<Xray>,
+    block_world: Res<BlockWorld>,
+    icons: Res<BlockIcons>,
+) {
+    // Only blocks of the world are listed, not the ones shown by entities like item frames
+    if names.is_empty() || block_world.is_changed() {
+        let mut found = HashSet::new();
+        let (sx, sy, sz) = block_world.blocks.size();
+        for x in 0..sx {
+            for y in 0..sy {
+                for z in 0..sz {
+                    found.insert(block_world.blocks.get_block_at(x, y, z));
+                }
+            }
+        }
+        *names = found
+            .into_iter()
+            .map(|block| split_block_state(block).0.trim_start_matches("minecraft:"))
+            .filter(|name| *name != "air")
+            .map(String::from)
+            .collect();
+        names.sort();
+        names.dedup();
+    }
+
+    // Edit a copy to only trigger change detection when something changed
+    let mut enabled = xray.enabled;
+    let mut filter = xray.filter.clone();
+    let mut save = false;
+
+    egui::Window::new("X-Ray")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut enabled, "Enabled (X)");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut filter.action, XrayAction::Hide, "Hide");
+                ui.radio_value(&mut filter.action, XrayAction::Ghost, "Ghost");
+            });
+            ui.checkbox(&mut filter.only, "Keep only the listed blocks");
+            ui.separator();
+
+            let mut removed = None;
+            for (idx, pattern) in filter.blocks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✕").clicked() {
+                        removed = Some(idx);
+                    }
+                    ui.monospace(pattern);
+                });
+            }
+            if let Some(idx) = removed {
+                filter.blocks.remove(idx);
+            }
+
+            ui.horizontal(|ui| {
+                let response = ui.text_edit_singleline(&mut *new_block);
+                let entered =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if (ui.button("Add").clicked() || entered) && !new_block.trim().is_empty() {
+                    filter.blocks.push(new_block.trim().to_string());
+                    new_block.clear();
+                }
+            });
+            egui::ComboBox::from_id_salt("xray_schematic_blocks")
+                .selected_text("Add from schematic")
+                .show_ui(ui, |ui| {
+                    for name in names.iter() {
+                        ui.horizontal(|ui| {
+                            let block = format!("minecraft:{}", name);
+                            if let Some(icon) = icons.block_icon(&block) {
+                                ui.add(icon);
+                            }
+                            if ui.selectable_label(false, name).clicked() {
+                                filter.blocks.push(name.to_string());
+                            }
+                        });
+                    }
+                });
+            ui.separator();
+
+            egui::ComboBox::from_id_salt("xray_presets")
+                .selected_text("Load preset")
+                .show_ui(ui, |ui| {
+                    for preset in &xray.presets {
+                        if ui.selectable_label(false, &preset.name).clicked() {
+                            filter = preset.clone();
+                            enabled = true;
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut filter.name);
+                save = ui.button("Save preset").clicked();
+            });
+        });
+
+    if enabled != xray.enabled {
+        xray.enabled = enabled;
+    }
+    if filter != xray.filter {
+        xray.filter = filter;
+    }
+    if save {
+        if let Err(err) = xray.save_preset() {
+            warn!("Could not save x-ray preset: {}", err);
+        }
+    }
+}
+
+pub struct XrayPlugin;
+
+impl Plugin for XrayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (toggle_xray, xray_window, apply_xray)
+                .chain()
+                .run_if(in_state(AppLoadState::Finished)),
+        );
+    }
+}